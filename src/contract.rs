@@ -1,31 +1,97 @@
-#[cfg(not(feature = "library"))]
-use crate::helpers::asset_info_from_string;
+use crate::helpers::{
+    asset_info_from_string, query_authz_grants, ACCEPTED_MESSAGE_KEYS_FILTER_TYPE_URL,
+    ALLOW_ALL_MESSAGES_FILTER_TYPE_URL, COMBINED_LIMIT_TYPE_URL,
+    CONTRACT_EXECUTION_AUTHORIZATION_TYPE_URL, GENERIC_AUTHORIZATION_TYPE_URL,
+    MAX_CALLS_LIMIT_TYPE_URL, MAX_FUNDS_LIMIT_TYPE_URL, MSG_EXECUTE_CONTRACT_TYPE_URL,
+    MSG_EXEC_TYPE_URL, MSG_SEND_TYPE_URL, MSG_WITHDRAW_DELEGATOR_REWARD_TYPE_URL,
+    MSG_WITHDRAW_VALIDATOR_COMMISSION_TYPE_URL, SEND_AUTHORIZATION_TYPE_URL,
+};
 use crate::msg::{
-    CollectFeeRequirement, ConfigResponse, DistributeTargetsResponse, ExecuteMsg, InstantiateMsg,
-    MigrateMsg, QueryMsg,
+    AllExecutorStatsResponse, ApproverSettingsResponse, AssetRegistryEntry, AssetRegistryResponse,
+    ChunkLimitsResponse, CollectFeeRequirement, CollectFeeRequirementResult, CollectFeeSkipReason,
+    CollectFeeStatus, CollectFeesResult, CollectMode, CollectSource, CollectThresholdsResponse,
+    CollectionHistoryResponse, ConfigResponse, DistributeTargetsResponse, ExecuteMsg,
+    ExecutorQuotaResponse, ExecutorStatsResponse, FailedCollectionsResponse, FeeReceiver,
+    FeeReceiversResponse, InstantiateMsg, MigrateMsg, NativeReservesResponse, OfferAssetsResponse,
+    QueryMsg, QueuedRemainderResponse, RegisteredApproverResponse, RegisteredApproversResponse,
+    RetentionsResponse, RevenueBySourceResponse, RevenueLabelsResponse, RoutersResponse,
+    SourceRevenue,
+};
+use crate::state::{
+    ApproverLimits, ApproverRoute, ApproverSettings, AssetRetention, ChunkLimit, ChunkSpent,
+    CollectThreshold, CollectionRecord, CollectionRun, Config, Cw20PullMode, DistributeTarget,
+    ExecutorQuota, ExecutorStats, ExecutorUsage, FailedCollection, KeeperBounty, NativeReserve,
+    PendingCollection, PermissionlessMode, RegisteredApprover, RouterInfo, RouterKind,
+    ALLOWED_OFFER_ASSETS, APPROVER_SETTINGS, ASSET_REGISTRY, CHUNK_LIMITS, CHUNK_SPENT,
+    COLLECTION_COUNT, COLLECTION_HISTORY, COLLECTION_RUN, COLLECTION_SNAPSHOT, COLLECT_THRESHOLDS,
+    CONFIG, DENIED_OFFER_ASSETS, DISTRIBUTION_TARGETS, EXECUTORS, EXECUTOR_QUOTAS, EXECUTOR_STATS,
+    EXECUTOR_USAGE, FAILED_COLLECTIONS, FEE_RECEIVERS, LAST_COLLECTED, NATIVE_RESERVES,
+    PENDING_CLAIMS, PENDING_COLLECTIONS, QUEUED_REMAINDERS, REGISTERED_APPROVERS, RETENTIONS,
+    RETRY_CURSOR, REVENUE_BY_SOURCE, REVENUE_LABELS, ROUTERS,
 };
-use crate::state::{Config, DistributeTarget, CONFIG, DISTRIBUTION_TARGETS, EXECUTORS};
 use crate::ContractError;
 use cosmos_sdk_proto::cosmos::authz::v1beta1::MsgExec;
-use cosmos_sdk_proto::cosmos::bank::v1beta1::MsgSend;
+use cosmos_sdk_proto::cosmos::bank::v1beta1::{MsgSend, SendAuthorization};
 use cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
+use cosmos_sdk_proto::cosmos::distribution::v1beta1::{
+    MsgWithdrawDelegatorReward, MsgWithdrawValidatorCommission,
+};
+use cosmos_sdk_proto::cosmwasm::wasm::v1::{
+    AcceptedMessageKeysFilter, CombinedLimit, ContractExecutionAuthorization, ContractGrant,
+    MaxCallsLimit, MsgExecuteContract,
+};
 use cosmos_sdk_proto::traits::{Message, MessageExt};
+use cosmos_sdk_proto::Any;
 
 use cosmwasm_std::{
-    entry_point, to_json_binary, Addr, CosmosMsg, Decimal, Storage, Uint128, WasmMsg,
+    coins, entry_point, from_json, to_json_binary, to_json_vec, Addr, BankMsg, BlockInfo,
+    CosmosMsg, Decimal, Event, Order, Reply, ReplyOn, Storage, SubMsg, SubMsgResult, Timestamp,
+    Uint128, WasmMsg,
 };
-use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
-use cw2::set_contract_version;
-use cw20::{BalanceResponse, Cw20ExecuteMsg};
+use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult};
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{AllowanceResponse, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw_storage_plus::{Bound, Map, PrefixBound};
 use oraiswap::asset::AssetInfo;
 use oraiswap::mixed_router::{
-    Cw20HookMsg as Cw20RouterHookMsg, ExecuteMsg as RouterExecuteMsg, SwapOperation,
+    Cw20HookMsg as Cw20RouterHookMsg, ExecuteMsg as RouterExecuteMsg, QueryMsg as RouterQueryMsg,
+    SwapOperation,
 };
+use oraiswap::router::{
+    Cw20HookMsg as LegacyRouterHookMsg, ExecuteMsg as LegacyRouterExecuteMsg,
+    QueryMsg as LegacyRouterQueryMsg, SimulateSwapOperationsResponse,
+    SwapOperation as LegacySwapOperation,
+};
+use oraiswap_v3::msg::ExecuteMsg as V3ExecuteMsg;
+use oraiswap_v3::sqrt_price::SqrtPrice;
+use oraiswap_v3::token_amount::TokenAmount;
+use oraiswap_v3::{MAX_SQRT_PRICE, MIN_SQRT_PRICE};
+use std::collections::BTreeMap;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:tresury";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// 1 orai is left in approver accounts for transaction fee unless the owner changes it
+const DEFAULT_NATIVE_RESERVE_DENOM: &str = "orai";
+const DEFAULT_NATIVE_RESERVE_AMOUNT: u128 = 1000000;
+// first version storing the native reserves, older versions had the orai reserve hard-coded
+const NATIVE_RESERVES_VERSION: [u64; 3] = [0, 3, 0];
+
+const MAX_BPS: u16 = 10000;
+
+// settings for pagination
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+// revenue of the collection labels is aggregated by daily epochs
+const REVENUE_EPOCH: u64 = 86_400;
+// epochs a single revenue query may span
+const MAX_REVENUE_EPOCHS: u64 = 366;
+
+// a failed collection leaves the retry queue after this many failed attempts
+const MAX_COLLECTION_ATTEMPTS: u32 = 5;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -42,6 +108,11 @@ pub fn instantiate(
             Some(addr) => Some(deps.api.addr_validate(addr.as_str())?),
             None => None,
         },
+        max_slippage_bps: None,
+        check_authz_grants: true,
+        keeper_bounty: None,
+        permissionless: None,
+        registered_routes_only: false,
     };
 
     CONFIG.save(deps.storage, &config)?;
@@ -70,6 +141,8 @@ pub fn instantiate(
         EXECUTORS.save(deps.storage, executor, &true).unwrap();
     });
 
+    save_default_native_reserve(deps.storage)?;
+
     Ok(Response::default())
 }
 
@@ -84,7 +157,17 @@ pub fn execute(
         ExecuteMsg::UpdateConfig {
             owner,
             distribute_token,
-        } => execute_update_config(deps, env, info, owner, distribute_token),
+            check_authz_grants,
+            registered_routes_only,
+        } => execute_update_config(
+            deps,
+            env,
+            info,
+            owner,
+            distribute_token,
+            check_authz_grants,
+            registered_routes_only,
+        ),
         ExecuteMsg::UpdateDistributeTarget { distribute_targets } => {
             execute_update_distribute_target(deps, env, info, distribute_targets)
         }
@@ -93,7 +176,87 @@ pub fn execute(
         }
         ExecuteMsg::CollectFees {
             collect_fee_requirements,
-        } => execute_collect_fees(deps, env, info, collect_fee_requirements),
+            auto_distribute,
+            mode,
+        } => execute_collect_fees(
+            deps,
+            env,
+            info,
+            collect_fee_requirements,
+            auto_distribute.unwrap_or_default(),
+            mode.unwrap_or_default(),
+        ),
+        ExecuteMsg::CollectRegisteredFees {
+            approvers,
+            auto_distribute,
+            mode,
+        } => execute_collect_registered_fees(
+            deps,
+            env,
+            info,
+            approvers,
+            auto_distribute.unwrap_or_default(),
+            mode.unwrap_or_default(),
+        ),
+        ExecuteMsg::RetryFailedCollections { limit } => {
+            execute_retry_failed_collections(deps, env, info, limit)
+        }
+        ExecuteMsg::RunCollection { messages } => execute_run_collection(env, info, messages),
+        ExecuteMsg::UpdateMaxSlippage { max_slippage_bps } => {
+            execute_update_max_slippage(deps, env, info, max_slippage_bps)
+        }
+        ExecuteMsg::UpdateKeeperBounty { keeper_bounty } => {
+            execute_update_keeper_bounty(deps, env, info, keeper_bounty)
+        }
+        ExecuteMsg::UpdatePermissionlessMode { permissionless } => {
+            execute_update_permissionless_mode(deps, env, info, permissionless)
+        }
+        ExecuteMsg::UpdateRouter { router } => execute_update_router(deps, env, info, router),
+        ExecuteMsg::RemoveRouter { name } => execute_remove_router(deps, env, info, name),
+        ExecuteMsg::UpdateFeeReceivers { receivers } => {
+            execute_update_fee_receivers(deps, env, info, receivers)
+        }
+        ExecuteMsg::UpdateRevenueLabels { labels, listed } => {
+            execute_update_revenue_labels(deps, env, info, labels, listed)
+        }
+        ExecuteMsg::UpdateAssetRegistry { entries } => {
+            execute_update_asset_registry(deps, env, info, entries)
+        }
+        ExecuteMsg::UpdateAllowedOfferAssets { assets, allowed } => {
+            execute_update_offer_asset_list(deps, info, ALLOWED_OFFER_ASSETS, assets, allowed)
+        }
+        ExecuteMsg::UpdateDeniedOfferAssets { assets, denied } => {
+            execute_update_offer_asset_list(deps, info, DENIED_OFFER_ASSETS, assets, denied)
+        }
+        ExecuteMsg::UpdateCollectThresholds { thresholds } => {
+            execute_update_collect_thresholds(deps, env, info, thresholds)
+        }
+        ExecuteMsg::UpdateChunkLimits { limits } => {
+            execute_update_chunk_limits(deps, env, info, limits)
+        }
+        ExecuteMsg::UpdateNativeReserves { reserves } => {
+            execute_update_native_reserves(deps, env, info, reserves)
+        }
+        ExecuteMsg::RegisterApprover { approver, routes } => {
+            execute_register_approver(deps, env, info, approver, routes)
+        }
+        ExecuteMsg::RemoveApprover { approver } => {
+            execute_remove_approver(deps, env, info, approver)
+        }
+        ExecuteMsg::RemoveFailedCollections { approver, ids } => {
+            execute_remove_failed_collections(deps, env, info, approver, ids)
+        }
+        ExecuteMsg::OptInCollection { limits } => {
+            execute_opt_in_collection(deps, env, info, limits)
+        }
+        ExecuteMsg::OptOutCollection {} => execute_opt_out_collection(deps, env, info),
+        ExecuteMsg::UpdateCw20PullMode { mode } => {
+            execute_update_cw20_pull_mode(deps, env, info, mode)
+        }
+        ExecuteMsg::UpdateRetentions {
+            approver,
+            retentions,
+        } => execute_update_retentions(deps, env, info, approver, retentions),
         ExecuteMsg::UpdateExecutors {
             executors,
             permission,
@@ -114,6 +277,9 @@ pub fn execute(
                 .add_attribute("action", "update_executors")
                 .add_attribute("permission", permission.to_string()))
         }
+        ExecuteMsg::UpdateExecutorQuota { executor, quota } => {
+            execute_update_executor_quota(deps, env, info, executor, quota)
+        }
     }
 }
 
@@ -123,6 +289,8 @@ fn execute_update_config(
     info: MessageInfo,
     owner: Option<Addr>,
     distribute_token: Option<Addr>,
+    check_authz_grants: Option<bool>,
+    registered_routes_only: Option<bool>,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     if config.owner != info.sender {
@@ -133,6 +301,11 @@ fn execute_update_config(
         owner: owner.unwrap_or(config.owner),
         distribute_token: distribute_token.unwrap_or(config.distribute_token),
         router: config.router,
+        max_slippage_bps: config.max_slippage_bps,
+        check_authz_grants: check_authz_grants.unwrap_or(config.check_authz_grants),
+        keeper_bounty: config.keeper_bounty,
+        permissionless: config.permissionless,
+        registered_routes_only: registered_routes_only.unwrap_or(config.registered_routes_only),
     };
 
     CONFIG.save(deps.storage, &new_config)?;
@@ -183,236 +356,2609 @@ fn execute_distribute(
         return Err(ContractError::Unauthorized {});
     }
 
-    let balance: BalanceResponse = deps.querier.query_wasm_smart(
-        config.distribute_token.clone(),
-        &cw20_base::msg::QueryMsg::Balance {
-            address: env.contract.address.into(),
-        },
-    )?;
+    let distribute_asset_info =
+        asset_info_from_string(deps.as_ref(), config.distribute_token.as_str())?;
+    let balance = distribute_asset_info.query_pool(&deps.querier, env.contract.address)?;
 
     balance
-        .balance
         .checked_sub(amount_distribute)
         .map_err(|_| ContractError::ExceedContractBalance {})?;
 
-    let messages = _load_target_messages(deps.storage, amount_distribute, config.distribute_token)?;
+    let messages = _load_target_messages(deps.storage, amount_distribute, &distribute_asset_info)?;
     Ok(Response::new()
         .add_messages(messages)
         .add_attribute("action", "distribute")
         .add_attribute("amount_distribute", amount_distribute.to_string()))
 }
 
-pub fn execute_collect_fees(
+fn execute_update_executor_quota(
     deps: DepsMut,
-    env: Env,
+    _env: Env,
     info: MessageInfo,
-    collect_fee_requirements: Vec<CollectFeeRequirement>,
+    executor: Addr,
+    quota: Option<ExecutorQuota>,
 ) -> Result<Response, ContractError> {
-    if !EXECUTORS
-        .load(deps.storage, &info.sender)
-        .unwrap_or_default()
-    {
+    if CONFIG.load(deps.storage)?.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let executor = deps.api.addr_validate(executor.as_str())?;
+    match &quota {
+        Some(quota) if quota.window == 0 => return Err(ContractError::InvalidExecutorQuota {}),
+        Some(quota) => EXECUTOR_QUOTAS.save(deps.storage, &executor, quota)?,
+        None => EXECUTOR_QUOTAS.remove(deps.storage, &executor),
+    }
+    // the new limits apply from a fresh window
+    EXECUTOR_USAGE.remove(deps.storage, &executor);
+
+    Ok(Response::new()
+        .add_attribute("action", "update_executor_quota")
+        .add_attribute("executor", executor.as_str()))
+}
+
+fn execute_update_max_slippage(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    max_slippage_bps: Option<u16>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if max_slippage_bps.unwrap_or_default() > MAX_BPS {
+        return Err(ContractError::InvalidSlippage {});
+    }
+
+    config.max_slippage_bps = max_slippage_bps;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("action", "update_max_slippage"))
+}
+
+fn execute_update_keeper_bounty(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    keeper_bounty: Option<KeeperBounty>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(bounty) = &keeper_bounty {
+        if bounty.bps > MAX_BPS {
+            return Err(ContractError::InvalidKeeperBounty {});
+        }
+    }
+
+    config.keeper_bounty = keeper_bounty;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("action", "update_keeper_bounty"))
+}
+
+fn execute_update_permissionless_mode(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    permissionless: Option<PermissionlessMode>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if permissionless.is_some() && !has_slippage_protection(deps.storage)? {
+        return Err(ContractError::MissingSlippageProtection {});
+    }
+
+    config.permissionless = permissionless;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("action", "update_permissionless_mode"))
+}
+
+/// Whether at least one offer asset has an absolute output bound. The quote behind
+/// `max_slippage_bps` is taken in the keeper's own transaction and can be skewed by it,
+/// so only a `min_output` threshold protects a permissionless swap.
+fn has_slippage_protection(storage: &dyn Storage) -> StdResult<bool> {
+    for threshold in COLLECT_THRESHOLDS.range(storage, None, None, Order::Ascending) {
+        if threshold?.1.min_output.is_some() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn execute_update_router(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    router: RouterInfo,
+) -> Result<Response, ContractError> {
+    if CONFIG.load(deps.storage)?.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let router = RouterInfo {
+        addr: deps.api.addr_validate(router.addr.as_str())?,
+        ..router
+    };
+    ROUTERS.save(deps.storage, &router.name, &router)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_router"),
+        ("name", router.name.as_str()),
+        ("router", router.addr.as_str()),
+        ("paused", &router.paused.to_string()),
+    ]))
+}
+
+fn execute_remove_router(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    name: String,
+) -> Result<Response, ContractError> {
+    if CONFIG.load(deps.storage)?.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    ROUTERS.remove(deps.storage, &name);
+
+    Ok(Response::new().add_attributes(vec![("action", "remove_router"), ("name", &name)]))
+}
+
+fn execute_update_native_reserves(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    reserves: Vec<NativeReserve>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    for reserve in reserves.iter() {
+        if reserve.percent.unwrap_or_default() > Decimal::one() {
+            return Err(ContractError::InvalidNativeReserve {
+                denom: reserve.denom.clone(),
+            });
+        }
+        // an empty reserve removes the denom, its whole balance can be collected again
+        if reserve.amount.is_zero() && reserve.percent.is_none() {
+            NATIVE_RESERVES.remove(deps.storage, &reserve.denom);
+        } else {
+            NATIVE_RESERVES.save(deps.storage, &reserve.denom, reserve)?;
+        }
+    }
+
+    Ok(Response::new().add_attribute("action", "update_native_reserves"))
+}
+
+fn execute_update_fee_receivers(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    receivers: Vec<FeeReceiver>,
+) -> Result<Response, ContractError> {
+    if CONFIG.load(deps.storage)?.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    for receiver in receivers.iter() {
+        match &receiver.addr {
+            Some(addr) => FEE_RECEIVERS.save(
+                deps.storage,
+                &receiver.name,
+                &deps.api.addr_validate(addr.as_str())?,
+            )?,
+            None => FEE_RECEIVERS.remove(deps.storage, &receiver.name),
+        }
+    }
+
+    Ok(Response::new().add_attribute("action", "update_fee_receivers"))
+}
+
+fn execute_update_revenue_labels(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    labels: Vec<String>,
+    listed: bool,
+) -> Result<Response, ContractError> {
+    if CONFIG.load(deps.storage)?.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    for label in labels.iter() {
+        if listed {
+            REVENUE_LABELS.save(deps.storage, label, &true)?;
+        } else {
+            REVENUE_LABELS.remove(deps.storage, label);
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_revenue_labels")
+        .add_attribute("listed", listed.to_string()))
+}
+
+fn execute_update_asset_registry(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    entries: Vec<AssetRegistryEntry>,
+) -> Result<Response, ContractError> {
+    if CONFIG.load(deps.storage)?.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    for entry in entries.iter() {
+        match &entry.asset_info {
+            Some(asset_info) => ASSET_REGISTRY.save(deps.storage, &entry.asset, asset_info)?,
+            None => ASSET_REGISTRY.remove(deps.storage, &entry.asset),
+        }
+    }
+
+    Ok(Response::new().add_attribute("action", "update_asset_registry"))
+}
+
+fn execute_update_offer_asset_list(
+    deps: DepsMut,
+    info: MessageInfo,
+    list: Map<&str, AssetInfo>,
+    assets: Vec<AssetInfo>,
+    listed: bool,
+) -> Result<Response, ContractError> {
+    if CONFIG.load(deps.storage)?.owner != info.sender {
         return Err(ContractError::Unauthorized {});
     }
 
+    for asset in assets.iter() {
+        if listed {
+            list.save(deps.storage, &asset.to_string(), asset)?;
+        } else {
+            list.remove(deps.storage, &asset.to_string());
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_offer_asset_policy")
+        .add_attribute("listed", listed.to_string()))
+}
+
+fn execute_update_collect_thresholds(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    thresholds: Vec<CollectThreshold>,
+) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
 
-    let mut messages: Vec<CosmosMsg> = vec![];
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    if config.router.is_none() {
-        return Err(ContractError::RouterAndApproverNotSet {});
+    for threshold in thresholds.iter() {
+        let asset_key = threshold.asset_info.to_string();
+        if threshold.min_amount.is_zero() && threshold.min_output.is_none() {
+            COLLECT_THRESHOLDS.remove(deps.storage, &asset_key);
+        } else {
+            COLLECT_THRESHOLDS.save(deps.storage, &asset_key, threshold)?;
+        }
     }
-    let router_unwrap = config.router.unwrap();
-    // create a new variable for better code readability
-    let fees_receiver = env.contract.address;
-    // convert 1 times to asset info
-    let distribute_asset_info =
-        asset_info_from_string(deps.api, config.distribute_token.clone().into());
-    // build swap operations
-    let approver_messages = collect_fee_requirements
-        .iter()
-        .map(|requirement| -> StdResult<Option<Vec<CosmosMsg>>> {
-            let operations = requirement.clone().swap_operations;
-
-            let offer_asset = if let SwapOperation::SwapV3 { pool_key, x_to_y } = &operations[0] {
-                if *x_to_y {
-                    asset_info_from_string(deps.api, pool_key.token_x.clone())
-                } else {
-                    asset_info_from_string(deps.api, pool_key.token_y.clone())
-                }
-            } else {
-                return Ok(None);
-            };
 
-            let final_ask_asset = if let SwapOperation::SwapV3 { pool_key, x_to_y } =
-                &operations[operations.len() - 1]
-            {
-                if *x_to_y {
-                    asset_info_from_string(deps.api, pool_key.token_y.clone())
-                } else {
-                    asset_info_from_string(deps.api, pool_key.token_x.clone())
-                }
-            } else {
-                return Ok(None);
-            };
+    Ok(Response::new().add_attribute("action", "update_collect_thresholds"))
+}
 
-            // final ask asset should be distribute token
-            if distribute_asset_info != final_ask_asset.clone() {
-                return Ok(None);
-            }
+fn execute_update_chunk_limits(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    limits: Vec<ChunkLimit>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
 
-            let balance = offer_asset
-                .query_pool(&deps.querier, requirement.approver.clone())
-                .unwrap_or_default();
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
 
-            if balance.is_zero() {
-                return Ok(None);
-            }
-            // Assume that the owner approve infinite allowance to the contract
-            match &offer_asset {
-                AssetInfo::Token { contract_addr } => {
-                    // transfer from only if distribute asset equals to offer_asset
-                    if distribute_asset_info == offer_asset.clone() {
-                        return Ok(Some(vec![CosmosMsg::Wasm(WasmMsg::Execute {
-                            contract_addr: contract_addr.clone().into(),
-                            msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
-                                owner: requirement.approver.to_string(),
-                                recipient: fees_receiver.to_string(),
-                                amount: balance,
-                            })?,
-                            funds: vec![],
-                        })]));
-                    }
+    for limit in limits.iter() {
+        let asset_key = limit.asset_info.to_string();
+        // a zero limit removes the asset, its whole balance is swapped at once again
+        if limit.max_per_block.is_zero() {
+            CHUNK_LIMITS.remove(deps.storage, &asset_key);
+        } else {
+            CHUNK_LIMITS.save(deps.storage, &asset_key, limit)?;
+        }
 
-                    Ok(Some(vec![CosmosMsg::Wasm(WasmMsg::Execute {
-                        contract_addr: contract_addr.clone().into(),
-                        msg: to_json_binary(&Cw20ExecuteMsg::SendFrom {
-                            owner: requirement.approver.to_string(),
-                            contract: router_unwrap.to_string(),
-                            amount: balance,
-                            msg: to_json_binary(&Cw20RouterHookMsg::ExecuteSwapOperations {
-                                operations,
-                                minimum_receive: requirement.minimum_receive,
-                                to: Some(fees_receiver.to_string()),
-                                affiliates: None,
-                            })?,
-                        })?,
-                        funds: vec![],
-                    })]))
-                }
-                // handle native token
-                AssetInfo::NativeToken { denom } => {
-                    let mut swap_amount = balance;
-                    if denom == "orai" {
-                        // Left 1 orai for transaction fee
-                        swap_amount = swap_amount
-                            .checked_sub(Uint128::from(1000000u128))
-                            .unwrap_or_default();
-                    }
+        // the queued remainders were split by the previous limit, the next collection queues them again
+        let approvers = QUEUED_REMAINDERS
+            .prefix(&asset_key)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<Addr>>>()?;
+        for approver in approvers.iter() {
+            QUEUED_REMAINDERS.remove(deps.storage, (&asset_key, approver));
+        }
+    }
 
-                    if swap_amount.is_zero() {
-                        return Ok(None);
-                    }
+    Ok(Response::new().add_attribute("action", "update_chunk_limits"))
+}
 
-                    let send = MsgSend {
-                        from_address: requirement.approver.to_string(),
-                        to_address: fees_receiver.to_string(),
-                        amount: vec![Coin {
-                            denom: denom.clone(),
-                            amount: swap_amount.to_string(),
-                        }],
-                    };
-                    let send_any_result = send.to_any();
-                    if send_any_result.is_err() {
-                        return Ok(None);
-                    }
+fn execute_register_approver(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    approver: Addr,
+    routes: Vec<ApproverRoute>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
 
-                    let stargate_value = Binary::from(
-                        MsgExec {
-                            grantee: fees_receiver.to_string(),
-                            msgs: vec![send_any_result.unwrap()],
-                        }
-                        .encode_to_vec(),
-                    );
-                    // transfer_from native token
-                    let stargate = CosmosMsg::Stargate {
-                        type_url: "/cosmos.authz.v1beta1.MsgExec".to_string(),
-                        value: stargate_value,
-                    };
-
-                    let wasm_swap = CosmosMsg::Wasm(WasmMsg::Execute {
-                        contract_addr: router_unwrap.to_string(),
-                        msg: to_json_binary(&RouterExecuteMsg::ExecuteSwapOperations {
-                            operations: operations.clone(),
-                            to: Some(fees_receiver.clone()),
-                            minimum_receive: requirement.minimum_receive,
-                            affiliates: None,
-                        })?,
-                        funds: vec![cosmwasm_std::Coin {
-                            denom: denom.clone(),
-                            amount: swap_amount,
-                        }],
-                    });
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
 
-                    Ok(Some(vec![stargate, wasm_swap]))
+    let approver = deps.api.addr_validate(approver.as_str())?;
+    let distribute_asset_info =
+        asset_info_from_string(deps.as_ref(), config.distribute_token.as_str())?;
+
+    // reject routes that would always be skipped at collection time
+    for route in routes.iter() {
+        let (_, final_ask_asset) = resolve_swap_assets(deps.as_ref(), &route.swap_operations)
+            .map_err(|reason| ContractError::InvalidApproverRoute {
+                reason: reason.to_string(),
+            })?;
+        if final_ask_asset != distribute_asset_info {
+            return Err(ContractError::InvalidApproverRoute {
+                reason: CollectFeeSkipReason::InvalidFinalAskAsset.to_string(),
+            });
+        }
+        resolve_fee_receiver(deps.as_ref(), route.fee_receiver.as_deref())
+            .and_then(|_| check_label(deps.as_ref(), route.label.as_deref()))
+            .map_err(|reason| ContractError::InvalidApproverRoute {
+                reason: reason.to_string(),
+            })?;
+        if let Some(name) = &route.router {
+            let router = ROUTERS.may_load(deps.storage, name)?.ok_or_else(|| {
+                ContractError::InvalidApproverRoute {
+                    reason: CollectFeeSkipReason::UnknownRouter.to_string(),
                 }
+            })?;
+            if !router_supports(&router.kind, &route.swap_operations) {
+                return Err(ContractError::InvalidApproverRoute {
+                    reason: CollectFeeSkipReason::UnsupportedSwapOperation.to_string(),
+                });
             }
-        })
-        .filter_map(|msgs| match msgs {
-            Ok(Some(cosmos_msgs)) => Some(cosmos_msgs),
-            _ => None,
-        })
-        .flatten()
-        .collect::<Vec<CosmosMsg>>();
+        }
+    }
 
-    messages.extend(approver_messages);
+    REGISTERED_APPROVERS.save(
+        deps.storage,
+        &approver,
+        &RegisteredApprover {
+            approver: approver.clone(),
+            routes: routes.clone(),
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_approver")
+        .add_attribute("approver", approver.as_str())
+        .add_attribute("routes", routes.len().to_string()))
+}
+
+fn execute_remove_approver(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    approver: Addr,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    REGISTERED_APPROVERS.remove(deps.storage, &approver);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_approver")
+        .add_attribute("approver", approver.as_str()))
+}
+
+fn execute_remove_failed_collections(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    approver: Addr,
+    ids: Option<Vec<u64>>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let ids = match ids {
+        Some(ids) => ids,
+        None => FAILED_COLLECTIONS
+            .prefix(&approver)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<u64>>>()?,
+    };
+    for id in &ids {
+        FAILED_COLLECTIONS.remove(deps.storage, (&approver, *id));
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_failed_collections")
+        .add_attribute("approver", approver.as_str())
+        .add_attribute("removed", ids.len().to_string()))
+}
+
+fn execute_opt_in_collection(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    limits: ApproverLimits,
+) -> Result<Response, ContractError> {
+    let mut approver_settings = APPROVER_SETTINGS
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    approver_settings.opted_out = false;
+    approver_settings.limits = limits;
+    APPROVER_SETTINGS.save(deps.storage, &info.sender, &approver_settings)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "opt_in_collection")
+        .add_attribute("approver", info.sender.as_str()))
+}
+
+fn execute_update_cw20_pull_mode(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    mode: Cw20PullMode,
+) -> Result<Response, ContractError> {
+    let mut approver_settings = APPROVER_SETTINGS
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    approver_settings.cw20_pull = mode;
+    APPROVER_SETTINGS.save(deps.storage, &info.sender, &approver_settings)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_cw20_pull_mode")
+        .add_attribute("approver", info.sender.as_str()))
+}
+
+fn execute_opt_out_collection(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut approver_settings = APPROVER_SETTINGS
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    approver_settings.opted_out = true;
+    APPROVER_SETTINGS.save(deps.storage, &info.sender, &approver_settings)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "opt_out_collection")
+        .add_attribute("approver", info.sender.as_str()))
+}
+
+fn execute_update_retentions(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    approver: Addr,
+    retentions: Vec<AssetRetention>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let approver = deps.api.addr_validate(approver.as_str())?;
+
+    if info.sender != approver && info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // approver and owner share a single entry per asset, the last update wins
+    for retention in retentions.iter() {
+        let asset_key = retention.asset_info.to_string();
+        if retention.percent.unwrap_or_default() > Decimal::one() {
+            return Err(ContractError::InvalidRetention { asset: asset_key });
+        }
+        // an empty retention removes the asset, its whole balance can be collected again
+        if retention.amount.is_zero() && retention.percent.is_none() {
+            RETENTIONS.remove(deps.storage, (&approver, asset_key.as_str()));
+        } else {
+            RETENTIONS.save(deps.storage, (&approver, asset_key.as_str()), retention)?;
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_retentions")
+        .add_attribute("approver", approver.as_str()))
+}
+
+fn execute_collect_registered_fees(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    approvers: Option<Vec<Addr>>,
+    auto_distribute: bool,
+    mode: CollectMode,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let permissionless = collect_permission(deps.storage, &config, &info.sender)?;
+
+    let registered_approvers = match approvers {
+        Some(approvers) => approvers
+            .iter()
+            .map(|approver| {
+                REGISTERED_APPROVERS
+                    .may_load(deps.storage, approver)?
+                    .ok_or(ContractError::ApproverNotRegistered {
+                        approver: approver.to_string(),
+                    })
+            })
+            .collect::<Result<Vec<RegisteredApprover>, ContractError>>()?,
+        None => REGISTERED_APPROVERS
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, registered_approver)| registered_approver))
+            .collect::<StdResult<Vec<RegisteredApprover>>>()?,
+    };
+
+    collect_fees(
+        deps,
+        env,
+        info.sender,
+        registered_requirements(&registered_approvers),
+        auto_distribute,
+        mode,
+        permissionless.map(|mode| mode.cooldown),
+    )
+}
+
+pub fn execute_collect_fees(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collect_fee_requirements: Vec<CollectFeeRequirement>,
+    auto_distribute: bool,
+    mode: CollectMode,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let permissionless = collect_permission(deps.storage, &config, &info.sender)?;
+
+    // routes supplied by anyone but an executor are ignored, only the registered
+    // routes of the requested approvers are collected
+    let collect_fee_requirements = match permissionless {
+        Some(_) => {
+            let mut registered_approvers: Vec<RegisteredApprover> = vec![];
+            for requirement in collect_fee_requirements.iter() {
+                if registered_approvers
+                    .iter()
+                    .any(|registered| registered.approver == requirement.approver)
+                {
+                    continue;
+                }
+                if let Some(registered) =
+                    REGISTERED_APPROVERS.may_load(deps.storage, &requirement.approver)?
+                {
+                    registered_approvers.push(registered);
+                }
+            }
+            registered_requirements(&registered_approvers)
+        }
+        None => collect_fee_requirements,
+    };
+
+    collect_fees(
+        deps,
+        env,
+        info.sender,
+        collect_fee_requirements,
+        auto_distribute,
+        mode,
+        permissionless.map(|mode| mode.cooldown),
+    )
+}
+
+fn execute_retry_failed_collections(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    if !EXECUTORS
+        .load(deps.storage, &info.sender)
+        .unwrap_or_default()
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let cursor = RETRY_CURSOR.may_load(deps.storage)?;
+    let start = cursor
+        .as_ref()
+        .map(|(approver, id)| Bound::exclusive((approver, *id)));
+    let mut failed_collections = FAILED_COLLECTIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    if let Some((approver, id)) = &cursor {
+        // wrap around to the first keys, up to the cursor so none is retried twice
+        let end = Bound::inclusive((approver, *id));
+        let wrapped = FAILED_COLLECTIONS
+            .range(deps.storage, None, Some(end), Order::Ascending)
+            .take(limit - failed_collections.len())
+            .collect::<StdResult<Vec<_>>>()?;
+        failed_collections.extend(wrapped);
+    }
+    if let Some(((approver, id), _)) = failed_collections.last() {
+        RETRY_CURSOR.save(deps.storage, &(approver.clone(), *id))?;
+    }
+
+    // retries are recorded again on failure, and leave the queue once collected
+    let requirements = failed_collections
+        .iter()
+        .map(|(_, failed_collection)| failed_collection.requirement.clone())
+        .collect();
+
+    let response = collect_fees(
+        deps.branch(),
+        env.clone(),
+        info.sender,
+        requirements,
+        false,
+        CollectMode::BestEffort,
+        None,
+    )?;
+
+    // a skipped retry counts as an attempt too, otherwise a requirement that can not be
+    // collected anymore would keep its retry slot forever
+    let result = from_json::<CollectFeesResult>(response.data.clone().unwrap_or_default())?;
+    let mut events = vec![];
+    for ((_, failed_collection), result) in failed_collections.iter().zip(result.requirements) {
+        let Some(skip_reason) = result.skip_reason else {
+            continue;
+        };
+        let (attempts, dropped) = queue_failed_collection(
+            deps.storage,
+            &env,
+            failed_collection.id,
+            &failed_collection.requirement,
+            &failed_collection.offer_asset,
+            &skip_reason.to_string(),
+        )?;
+        events.push(
+            Event::new("retry_skipped")
+                .add_attribute("collection_id", failed_collection.id.to_string())
+                .add_attribute("approver", failed_collection.requirement.approver.as_str())
+                .add_attribute("skip_reason", skip_reason.to_string())
+                .add_attribute("attempts", attempts.to_string())
+                .add_attribute("dropped", dropped.to_string()),
+        );
+    }
+
+    Ok(response.add_events(events))
+}
+
+fn execute_run_collection(
+    env: Env,
+    info: MessageInfo,
+    messages: Vec<CosmosMsg>,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "run_collection"))
+}
+
+/// Returns the permissionless mode the sender collects under, `None` for executors.
+fn collect_permission(
+    storage: &dyn Storage,
+    config: &Config,
+    sender: &Addr,
+) -> Result<Option<PermissionlessMode>, ContractError> {
+    if EXECUTORS.load(storage, sender).unwrap_or_default() {
+        return Ok(None);
+    }
+
+    match &config.permissionless {
+        Some(mode) => Ok(Some(mode.clone())),
+        None => Err(ContractError::Unauthorized {}),
+    }
+}
+
+/// Requirements must match a registered route of their approver, only the minimum receive
+/// and the claimed source are left to the executor.
+fn check_registered_route(
+    deps: Deps,
+    requirement: &CollectFeeRequirement,
+) -> Result<(), CollectFeeSkipReason> {
+    let registered = REGISTERED_APPROVERS
+        .may_load(deps.storage, &requirement.approver)?
+        .map_or(false, |registered_approver| {
+            registered_approver.routes.iter().any(|route| {
+                route.swap_operations == requirement.swap_operations
+                    && route.router == requirement.router
+                    && route.fee_receiver == requirement.fee_receiver
+                    && route.label == requirement.label
+            })
+        });
+    match registered {
+        true => Ok(()),
+        false => Err(CollectFeeSkipReason::UnregisteredRoute),
+    }
+}
+
+fn registered_requirements(
+    registered_approvers: &[RegisteredApprover],
+) -> Vec<CollectFeeRequirement> {
+    registered_approvers
+        .iter()
+        .flat_map(|registered_approver| {
+            registered_approver
+                .routes
+                .iter()
+                .map(move |route| CollectFeeRequirement {
+                    approver: registered_approver.approver.clone(),
+                    swap_operations: route.swap_operations.clone(),
+                    minimum_receive: None,
+                    router: route.router.clone(),
+                    source: None,
+                    fee_receiver: route.fee_receiver.clone(),
+                    label: route.label.clone(),
+                })
+        })
+        .collect()
+}
+
+fn collect_fees(
+    deps: DepsMut,
+    env: Env,
+    executor: Addr,
+    collect_fee_requirements: Vec<CollectFeeRequirement>,
+    auto_distribute: bool,
+    mode: CollectMode,
+    permissionless_cooldown: Option<u64>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    consume_executor_call(deps.storage, &env.block, &executor)?;
+
+    let mut messages: Vec<SubMsg> = vec![];
+
+    let ctx = collect_context(
+        deps.as_ref(),
+        env,
+        &config,
+        &executor,
+        permissionless_cooldown,
+    )?;
+
+    let mut results: Vec<CollectFeeRequirementResult> = vec![];
+    let mut events: Vec<Event> = vec![];
+    let mut collection_count = COLLECTION_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    // pulls only run after this call, so amounts queued earlier in the batch are still in the balances
+    let mut taken: BTreeMap<(Addr, String), Uint128> = BTreeMap::new();
+
+    for requirement in collect_fee_requirements.iter() {
+        let mut result = new_result(&requirement.approver);
+
+        let step =
+            check_approver(deps.as_ref(), &ctx, &requirement.approver).and_then(|settings| {
+                if config.registered_routes_only {
+                    check_registered_route(deps.as_ref(), requirement)?;
+                }
+                match &requirement.source {
+                    Some(source) => {
+                        build_claim_message(deps.as_ref(), &ctx, requirement, source, &mut result)
+                            .map(CollectStep::Claim)
+                    }
+                    None => build_collect_fee_messages(
+                        deps.as_ref(),
+                        &ctx,
+                        requirement,
+                        &settings,
+                        &taken,
+                        &mut result,
+                    )
+                    .map(CollectStep::Swap),
+                }
+            });
+
+        match step {
+            Ok(CollectStep::Swap(approver_messages)) => {
+                *taken
+                    .entry((
+                        requirement.approver.clone(),
+                        approver_messages.offer_asset.to_string(),
+                    ))
+                    .or_default() += result.amount;
+                consume_executor_value(
+                    deps.storage,
+                    &ctx.block,
+                    &executor,
+                    approver_messages.value,
+                )?;
+                collection_count += 1;
+                result.collection_id = Some(collection_count);
+                messages.extend(queue_collection(
+                    deps.storage,
+                    &ctx,
+                    collection_count,
+                    requirement,
+                    &result,
+                    approver_messages,
+                    &mode,
+                )?);
+            }
+            Ok(CollectStep::Claim(claim_message)) => {
+                // the fees are only known once claimed, the requirement is collected in reply
+                collection_count += 1;
+                PENDING_CLAIMS.save(deps.storage, collection_count, requirement)?;
+                result.status = CollectFeeStatus::Claimed;
+                messages.push(match mode {
+                    CollectMode::Atomic => {
+                        SubMsg::reply_on_success(claim_message, collection_count)
+                    }
+                    CollectMode::BestEffort => {
+                        SubMsg::reply_always(claim_message, collection_count)
+                    }
+                });
+            }
+            Err(reason) => {
+                result.status = skipped_status(&reason);
+                result.skip_reason = Some(reason);
+            }
+        }
+
+        // the cooldown only starts once something was actually collected from the approver,
+        // later requirements of the same approver in this batch are already held back by it
+        if matches!(
+            result.status,
+            CollectFeeStatus::Collected | CollectFeeStatus::Claimed
+        ) {
+            LAST_COLLECTED.save(deps.storage, &result.approver, &ctx.block.time)?;
+        }
+
+        events.push(collect_fee_event(&result));
+        results.push(result);
+    }
+
+    let count_status = |status: CollectFeeStatus| {
+        results
+            .iter()
+            .filter(|result| result.status == status)
+            .count()
+            .to_string()
+    };
+    let collected = count_status(CollectFeeStatus::Collected);
+    let claimed = count_status(CollectFeeStatus::Claimed);
+    let skipped = count_status(CollectFeeStatus::Skipped);
+    let rejected = count_status(CollectFeeStatus::Rejected);
+
+    record_executor_call(deps.storage, &ctx.block, &executor, &results)?;
+
+    let mut response = Response::new();
+
+    if !messages.is_empty() {
+        COLLECTION_RUN.save(
+            deps.storage,
+            &CollectionRun {
+                executor,
+                auto_distribute,
+                // pulls never reply, every swap and claim replies once
+                pending: messages
+                    .iter()
+                    .filter(|message| {
+                        matches!(message.reply_on, ReplyOn::Success | ReplyOn::Always)
+                    })
+                    .count() as u64,
+                received: Uint128::zero(),
+                mode,
+                taken: taken
+                    .into_iter()
+                    .map(|((approver, asset_key), amount)| (approver, asset_key, amount))
+                    .collect(),
+            },
+        )?;
+        COLLECTION_COUNT.save(deps.storage, &collection_count)?;
+        // proceeds of every swap are measured against this balance
+        COLLECTION_SNAPSHOT.save(
+            deps.storage,
+            &ctx.distribute_asset_info
+                .query_pool(&deps.querier, ctx.fees_receiver.clone())?,
+        )?;
+        response = response.add_submessages(messages);
+    }
+
+    Ok(response
+        .add_events(events)
+        .add_attribute("action", "collect_fees")
+        .add_attribute("collected", collected)
+        .add_attribute("claimed", claimed)
+        .add_attribute("skipped", skipped)
+        .add_attribute("rejected", rejected)
+        .set_data(to_json_binary(&CollectFeesResult {
+            requirements: results,
+        })?))
+}
+
+/// Returns the usage of the executor in its current window, a new window starts once the last one ended.
+fn executor_usage(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    executor: &Addr,
+    quota: &ExecutorQuota,
+) -> StdResult<ExecutorUsage> {
+    let usage = EXECUTOR_USAGE
+        .may_load(storage, executor)?
+        .filter(|usage| block.time < usage.window_start.plus_seconds(quota.window));
+    Ok(usage.unwrap_or(ExecutorUsage {
+        window_start: block.time,
+        calls: 0,
+        value: Uint128::zero(),
+    }))
+}
+
+/// Counts a collection call against the quota of the executor.
+fn consume_executor_call(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    executor: &Addr,
+) -> Result<(), ContractError> {
+    let Some(quota) = EXECUTOR_QUOTAS.may_load(storage, executor)? else {
+        return Ok(());
+    };
+
+    let mut usage = executor_usage(storage, block, executor, &quota)?;
+    let calls_exceeded = quota
+        .max_calls
+        .map_or(false, |max_calls| usage.calls >= max_calls);
+    let value_exceeded = quota
+        .max_value
+        .map_or(false, |max_value| usage.value >= max_value);
+    if calls_exceeded || value_exceeded {
+        return Err(ContractError::ExecutorQuotaExceeded {
+            executor: executor.to_string(),
+        });
+    }
+
+    usage.calls += 1;
+    EXECUTOR_USAGE.save(storage, executor, &usage)?;
+    Ok(())
+}
+
+/// Updates the activity counters of the executor with a collection call.
+/// Permissionless keepers are not tracked, anyone could otherwise fill the stats.
+fn record_executor_call(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    executor: &Addr,
+    results: &[CollectFeeRequirementResult],
+) -> StdResult<()> {
+    if !EXECUTORS.load(storage, executor).unwrap_or_default() {
+        return Ok(());
+    }
+    EXECUTOR_STATS.update(storage, executor, |stats| -> StdResult<_> {
+        let mut stats = stats.unwrap_or(ExecutorStats {
+            executor: executor.clone(),
+            calls: 0,
+            requirements: 0,
+            skipped: 0,
+            last_call_height: 0,
+            last_call_time: Timestamp::default(),
+            total_proceeds: Uint128::zero(),
+        });
+        stats.calls += 1;
+        stats.requirements += results.len() as u64;
+        stats.skipped += results
+            .iter()
+            .filter(|result| result.skip_reason.is_some())
+            .count() as u64;
+        stats.last_call_height = block.height;
+        stats.last_call_time = block.time;
+        Ok(stats)
+    })?;
+    Ok(())
+}
+
+/// Counts the quoted value of a queued collection against the quota of the executor,
+/// a call offering more than the value left in the window is reverted.
+fn consume_executor_value(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    executor: &Addr,
+    value: Uint128,
+) -> Result<(), ContractError> {
+    let Some(quota) = EXECUTOR_QUOTAS.may_load(storage, executor)? else {
+        return Ok(());
+    };
+
+    let mut usage = executor_usage(storage, block, executor, &quota)?;
+    usage.value += value;
+    if quota
+        .max_value
+        .map_or(false, |max_value| usage.value > max_value)
+    {
+        return Err(ContractError::ExecutorQuotaExceeded {
+            executor: executor.to_string(),
+        });
+    }
+
+    EXECUTOR_USAGE.save(storage, executor, &usage)?;
+    Ok(())
+}
+
+fn new_result(approver: &Addr) -> CollectFeeRequirementResult {
+    CollectFeeRequirementResult {
+        approver: approver.clone(),
+        offer_asset: None,
+        amount: Uint128::zero(),
+        status: CollectFeeStatus::Collected,
+        minimum_receive: None,
+        collection_id: None,
+        router: None,
+        skip_reason: None,
+    }
+}
+
+fn collect_context(
+    deps: Deps,
+    env: Env,
+    config: &Config,
+    executor: &Addr,
+    permissionless_cooldown: Option<u64>,
+) -> Result<CollectContext, ContractError> {
+    let Some(router) = config.router.clone() else {
+        return Err(ContractError::RouterAndApproverNotSet {});
+    };
+    let value_quota = EXECUTOR_QUOTAS
+        .may_load(deps.storage, executor)?
+        .map_or(false, |quota| quota.max_value.is_some());
+
+    Ok(CollectContext {
+        router,
+        // create a new variable for better code readability
+        fees_receiver: env.contract.address,
+        // convert 1 times to asset info
+        distribute_asset_info: asset_info_from_string(deps, config.distribute_token.as_str())?,
+        block: env.block,
+        max_slippage_bps: config.max_slippage_bps,
+        check_authz_grants: config.check_authz_grants,
+        permissionless_cooldown,
+        value_quota,
+    })
+}
+
+/// Saves the pending collection of a requirement and returns its messages,
+/// the swap being measured in reply under `collection_id`.
+fn queue_collection(
+    storage: &mut dyn Storage,
+    ctx: &CollectContext,
+    collection_id: u64,
+    requirement: &CollectFeeRequirement,
+    result: &CollectFeeRequirementResult,
+    approver_messages: CollectFeeMessages,
+    mode: &CollectMode,
+) -> StdResult<Vec<SubMsg>> {
+    if let Some(remainder) = approver_messages.remainder {
+        let asset_key = approver_messages.offer_asset.to_string();
+        CHUNK_SPENT.update(storage, &asset_key, |spent| -> StdResult<_> {
+            let spent = spent
+                .filter(|spent| spent.height == ctx.block.height)
+                .map(|spent| spent.amount)
+                .unwrap_or_default();
+            Ok(ChunkSpent {
+                height: ctx.block.height,
+                amount: spent + result.amount,
+            })
+        })?;
+
+        let remainder_key = (asset_key.as_str(), &result.approver);
+        if remainder.is_zero() {
+            QUEUED_REMAINDERS.remove(storage, remainder_key);
+        } else {
+            QUEUED_REMAINDERS.save(storage, remainder_key, &remainder)?;
+        }
+    }
+
+    PENDING_COLLECTIONS.save(
+        storage,
+        collection_id,
+        &PendingCollection {
+            requirement: requirement.clone(),
+            offer_asset: approver_messages.offer_asset,
+            offer_amount: result.amount,
+            minimum_receive: approver_messages.reply_minimum_receive,
+            fee_receiver: approver_messages.fee_receiver,
+        },
+    )?;
+
+    let swap_message = approver_messages.swap_message;
+    let pull_messages = approver_messages.pull_messages;
+    let messages = match mode {
+        CollectMode::Atomic => pull_messages
+            .into_iter()
+            .map(SubMsg::new)
+            .chain([SubMsg::reply_on_success(swap_message, collection_id)])
+            .collect(),
+        // the swap must never spend fees that were not pulled, so both revert together
+        CollectMode::BestEffort if !pull_messages.is_empty() => {
+            let messages = pull_messages.into_iter().chain([swap_message]).collect();
+            vec![SubMsg::reply_always(
+                WasmMsg::Execute {
+                    contract_addr: ctx.fees_receiver.to_string(),
+                    msg: to_json_binary(&ExecuteMsg::RunCollection { messages })?,
+                    funds: vec![],
+                },
+                collection_id,
+            )]
+        }
+        CollectMode::BestEffort => vec![SubMsg::reply_always(swap_message, collection_id)],
+    };
+    Ok(messages)
+}
+
+/// Values shared by every requirement of a single `CollectFees` call.
+struct CollectContext {
+    router: Addr,
+    fees_receiver: Addr,
+    distribute_asset_info: AssetInfo,
+    block: BlockInfo,
+    max_slippage_bps: Option<u16>,
+    check_authz_grants: bool,
+    /// cooldown applied to every approver when collecting without being an executor
+    permissionless_cooldown: Option<u64>,
+    /// the executor has a value quota, so every swap is quoted before it is queued
+    value_quota: bool,
+}
+
+/// Messages collecting a single requirement.
+struct CollectFeeMessages {
+    offer_asset: AssetInfo,
+    /// messages moving the fees into the contract before the swap
+    pull_messages: Vec<CosmosMsg>,
+    /// message delivering the distribute token to the fees receiver
+    swap_message: CosmosMsg,
+    /// minimum receive checked in reply, for swaps that can not enforce one themselves
+    reply_minimum_receive: Option<Uint128>,
+    /// amount left for later runs when the offer asset is swapped in chunks
+    remainder: Option<Uint128>,
+    /// receiver the proceeds are forwarded to
+    fee_receiver: Option<Addr>,
+    /// offered amount quoted in the distribute token, counted against the value quota
+    value: Uint128,
+}
+
+enum CollectStep {
+    Swap(CollectFeeMessages),
+    Claim(CosmosMsg),
+}
+
+/// Returns the settings of an approver that can currently be collected.
+fn check_approver(
+    deps: Deps,
+    ctx: &CollectContext,
+    approver: &Addr,
+) -> Result<ApproverSettings, CollectFeeSkipReason> {
+    let approver_settings = APPROVER_SETTINGS
+        .may_load(deps.storage, approver)?
+        .unwrap_or_default();
+
+    if approver_settings.opted_out {
+        return Err(CollectFeeSkipReason::ApproverOptedOut);
+    }
+
+    let cooldown = approver_settings.limits.cooldown;
+    if let Some(cooldown) = cooldown.max(ctx.permissionless_cooldown) {
+        if let Some(last_collected) = LAST_COLLECTED.may_load(deps.storage, approver)? {
+            if ctx.block.time < last_collected.plus_seconds(cooldown) {
+                return Err(CollectFeeSkipReason::ApproverCooldown);
+            }
+        }
+    }
+
+    Ok(approver_settings)
+}
+
+/// Rejects denied offer assets, and any asset missing from a non empty allowlist.
+fn check_offer_asset_policy(
+    storage: &dyn Storage,
+    offer_asset: &AssetInfo,
+) -> Result<(), CollectFeeSkipReason> {
+    let asset_key = offer_asset.to_string();
+    if DENIED_OFFER_ASSETS.has(storage, &asset_key) {
+        return Err(CollectFeeSkipReason::OfferAssetDenied);
+    }
+
+    let allowlist_empty = ALLOWED_OFFER_ASSETS
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_none();
+    if !allowlist_empty && !ALLOWED_OFFER_ASSETS.has(storage, &asset_key) {
+        return Err(CollectFeeSkipReason::OfferAssetNotAllowed);
+    }
+
+    Ok(())
+}
+
+fn skipped_status(reason: &CollectFeeSkipReason) -> CollectFeeStatus {
+    if reason.is_policy_rejection() {
+        CollectFeeStatus::Rejected
+    } else {
+        CollectFeeStatus::Skipped
+    }
+}
+
+/// Builds the authz message claiming the fees of `source` into the approver account.
+fn build_claim_message(
+    deps: Deps,
+    ctx: &CollectContext,
+    requirement: &CollectFeeRequirement,
+    source: &CollectSource,
+    result: &mut CollectFeeRequirementResult,
+) -> Result<CosmosMsg, CollectFeeSkipReason> {
+    // do not claim anything that could not be swapped afterwards
+    let (offer_asset, final_ask_asset) = resolve_swap_assets(deps, &requirement.swap_operations)?;
+    result.offer_asset = Some(offer_asset.clone());
+    check_offer_asset_policy(deps.storage, &offer_asset)?;
+    if ctx.distribute_asset_info != final_ask_asset {
+        return Err(CollectFeeSkipReason::InvalidFinalAskAsset);
+    }
+    resolve_fee_receiver(deps, requirement.fee_receiver.as_deref())?;
+    check_label(deps, requirement.label.as_deref())?;
+
+    let claim_msgs = match source {
+        CollectSource::V3PositionFees {
+            contract,
+            position_indexes,
+        } => position_indexes
+            .iter()
+            .map(|index| {
+                Ok(Any {
+                    type_url: MSG_EXECUTE_CONTRACT_TYPE_URL.to_string(),
+                    value: MsgExecuteContract {
+                        sender: requirement.approver.to_string(),
+                        contract: contract.to_string(),
+                        msg: to_json_vec(&V3ExecuteMsg::ClaimFee { index: *index })?,
+                        funds: vec![],
+                    }
+                    .encode_to_vec(),
+                })
+            })
+            .collect::<StdResult<Vec<Any>>>()?,
+        CollectSource::StakingRewards {
+            validators,
+            validator_commission,
+        } => validators
+            .iter()
+            .map(|validator| Any {
+                type_url: MSG_WITHDRAW_DELEGATOR_REWARD_TYPE_URL.to_string(),
+                value: MsgWithdrawDelegatorReward {
+                    delegator_address: requirement.approver.to_string(),
+                    validator_address: validator.clone(),
+                }
+                .encode_to_vec(),
+            })
+            .chain(validator_commission.iter().map(|validator| {
+                Any {
+                    type_url: MSG_WITHDRAW_VALIDATOR_COMMISSION_TYPE_URL.to_string(),
+                    value: MsgWithdrawValidatorCommission {
+                        validator_address: validator.clone(),
+                    }
+                    .encode_to_vec(),
+                }
+            }))
+            .collect(),
+    };
+    if claim_msgs.is_empty() {
+        return Err(CollectFeeSkipReason::NothingToClaim);
+    }
+
+    Ok(CosmosMsg::Stargate {
+        type_url: MSG_EXEC_TYPE_URL.to_string(),
+        value: Binary::from(
+            MsgExec {
+                grantee: ctx.fees_receiver.to_string(),
+                msgs: claim_msgs,
+            }
+            .encode_to_vec(),
+        ),
+    })
+}
+
+/// Builds the messages pulling and swapping the fees of a single requirement.
+/// `taken` holds the amounts already queued earlier in the batch, per approver and offer asset.
+/// `result` is filled with the offer asset and amount as soon as they are known,
+/// so that a skipped requirement still reports how far it got.
+fn build_collect_fee_messages(
+    deps: Deps,
+    ctx: &CollectContext,
+    requirement: &CollectFeeRequirement,
+    settings: &ApproverSettings,
+    taken: &BTreeMap<(Addr, String), Uint128>,
+    result: &mut CollectFeeRequirementResult,
+) -> Result<CollectFeeMessages, CollectFeeSkipReason> {
+    let limits = &settings.limits;
+    let cw20_pull = &settings.cw20_pull;
+    let operations = requirement.swap_operations.clone();
+
+    let (offer_asset, final_ask_asset) = resolve_swap_assets(deps, &operations)?;
+    result.offer_asset = Some(offer_asset.clone());
+    check_offer_asset_policy(deps.storage, &offer_asset)?;
+
+    // final ask asset should be distribute token
+    if ctx.distribute_asset_info != final_ask_asset {
+        return Err(CollectFeeSkipReason::InvalidFinalAskAsset);
+    }
+
+    // every hop must end in an asset the approver accepts, the distribute token always is
+    if let Some(allowed_ask_assets) = &limits.allowed_ask_assets {
+        for ask_asset in resolve_ask_assets(deps, &operations)? {
+            if ask_asset != ctx.distribute_asset_info && !allowed_ask_assets.contains(&ask_asset) {
+                return Err(CollectFeeSkipReason::AskAssetNotAllowed);
+            }
+        }
+    }
+
+    let (router_addr, router_kind) = select_router(deps, ctx, requirement.router.as_deref())?;
+    if !router_supports(&router_kind, &operations) {
+        return Err(CollectFeeSkipReason::UnsupportedSwapOperation);
+    }
+    let fee_receiver = resolve_fee_receiver(deps, requirement.fee_receiver.as_deref())?;
+    check_label(deps, requirement.label.as_deref())?;
+
+    let asset_key = offer_asset.to_string();
+    let already_taken = taken
+        .get(&(requirement.approver.clone(), asset_key.clone()))
+        .copied()
+        .unwrap_or_default();
+    let balance = offer_asset
+        .query_pool(&deps.querier, requirement.approver.clone())
+        .unwrap_or_default();
+
+    if balance.is_zero() {
+        return Err(CollectFeeSkipReason::ZeroBalance);
+    }
+
+    // Left some native token in the approver account for transaction fee
+    let gas_reserve = match &offer_asset {
+        AssetInfo::NativeToken { denom } => NATIVE_RESERVES
+            .may_load(deps.storage, denom)?
+            .map(|reserve| {
+                reserve
+                    .amount
+                    .max(balance * reserve.percent.unwrap_or_default())
+            })
+            .unwrap_or_default(),
+        AssetInfo::Token { .. } => Uint128::zero(),
+    };
+    if balance <= gas_reserve {
+        return Err(CollectFeeSkipReason::BelowGasReserve);
+    }
+
+    let retained = RETENTIONS
+        .may_load(
+            deps.storage,
+            (&requirement.approver, offer_asset.to_string().as_str()),
+        )?
+        .map(|retention| {
+            retention
+                .amount
+                .max(balance * retention.percent.unwrap_or_default())
+        })
+        .unwrap_or_default();
+    let min_balance = limits.min_balance.unwrap_or_default().max(retained);
+    if balance <= min_balance {
+        return Err(CollectFeeSkipReason::BelowMinBalance);
+    }
+
+    let mut amount = (balance - gas_reserve.max(min_balance)).saturating_sub(already_taken);
+    if let Some(max_per_collection) = limits.max_per_collection {
+        let cap = max_per_collection.saturating_sub(already_taken);
+        if cap.is_zero() {
+            return Err(CollectFeeSkipReason::CollectionCapReached);
+        }
+        amount = amount.min(cap);
+    }
+    if amount.is_zero() {
+        return Err(CollectFeeSkipReason::ZeroBalance);
+    }
+
+    // never try to pull more than the approver granted, otherwise the whole batch reverts
+    let granted = query_granted_amount(deps, ctx, cw20_pull, &requirement.approver, &offer_asset)?
+        .saturating_sub(already_taken);
+    if granted.is_zero() {
+        return Err(match offer_asset {
+            AssetInfo::Token { .. } if *cw20_pull == Cw20PullMode::Allowance => {
+                CollectFeeSkipReason::InsufficientAllowance
+            }
+            _ => CollectFeeSkipReason::NoAuthzGrant,
+        });
+    }
+    let amount = amount.min(granted);
+    result.amount = amount;
+
+    // dust is not worth the gas and router fees of collecting it
+    // the whole collectable amount counts, a chunk of it may be small on purpose
+    let threshold = COLLECT_THRESHOLDS.may_load(deps.storage, &asset_key)?;
+    if let Some(threshold) = &threshold {
+        if amount < threshold.min_amount {
+            return Err(CollectFeeSkipReason::BelowThreshold);
+        }
+    }
+
+    // transfer from only if distribute asset equals to offer_asset
+    if ctx.distribute_asset_info == offer_asset {
+        let transfer_message = match &offer_asset {
+            AssetInfo::Token { contract_addr } => {
+                cw20_transfer_message(ctx, cw20_pull, &requirement.approver, contract_addr, amount)?
+            }
+            AssetInfo::NativeToken { denom } => {
+                native_pull_message(ctx, &requirement.approver, denom, amount)?
+            }
+        };
+        return Ok(CollectFeeMessages {
+            offer_asset: offer_asset.clone(),
+            pull_messages: vec![],
+            swap_message: transfer_message,
+            reply_minimum_receive: None,
+            remainder: None,
+            fee_receiver,
+            value: amount,
+        });
+    }
+
+    // large balances are swapped in chunks over several blocks to limit the price impact
+    let collectable = amount;
+    let (amount, remainder) = match CHUNK_LIMITS.may_load(deps.storage, &asset_key)? {
+        Some(chunk_limit) => {
+            let spent = CHUNK_SPENT
+                .may_load(deps.storage, &asset_key)?
+                .filter(|spent| spent.height == ctx.block.height)
+                .map(|spent| spent.amount)
+                .unwrap_or_default();
+            let chunk = chunk_limit.max_per_block.saturating_sub(spent);
+            if chunk.is_zero() {
+                return Err(CollectFeeSkipReason::ChunkLimitReached);
+            }
+            (amount.min(chunk), Some(amount.saturating_sub(chunk)))
+        }
+        None => (amount, None),
+    };
+    result.amount = amount;
+
+    result.router = Some(router_addr.clone());
+    // a single router quote backs both the output threshold and the slippage protection,
+    // a chunk only has to reach its share of the minimum output
+    let min_output = threshold
+        .as_ref()
+        .and_then(|threshold| threshold.min_output)
+        .map(|min_output| min_output.multiply_ratio(amount, collectable));
+    // keepers choose when to swap and can move the quote within their own transaction,
+    // so only an absolute output bound protects a permissionless swap
+    let permissionless = ctx.permissionless_cooldown.is_some();
+    if permissionless && min_output.is_none() {
+        return Err(CollectFeeSkipReason::NoSlippageProtection);
+    }
+    let simulated = match ctx.max_slippage_bps.is_some() || min_output.is_some() || ctx.value_quota
+    {
+        true => Some(simulate_swap(
+            deps,
+            ctx,
+            &router_addr,
+            &router_kind,
+            &operations,
+            amount,
+        )?),
+        false => None,
+    };
+    if let (Some(min_output), Some(simulated)) = (min_output, simulated) {
+        if simulated < min_output {
+            return Err(CollectFeeSkipReason::BelowMinOutput);
+        }
+    }
+
+    // raise the executor supplied minimum receive to the quote minus the allowed slippage
+    let minimum_receive = match (ctx.max_slippage_bps, simulated) {
+        (Some(max_slippage_bps), Some(simulated)) => {
+            let slippage_floor =
+                simulated * Decimal::from_ratio(MAX_BPS - max_slippage_bps, MAX_BPS);
+            Some(
+                requirement
+                    .minimum_receive
+                    .unwrap_or_default()
+                    .max(slippage_floor),
+            )
+        }
+        _ => requirement.minimum_receive,
+    };
+    let minimum_receive = match permissionless {
+        true => minimum_receive.max(min_output),
+        false => minimum_receive,
+    };
+    result.minimum_receive = minimum_receive;
+
+    let mut pull_messages = vec![];
+    let funds = match &offer_asset {
+        AssetInfo::Token { .. } => vec![],
+        AssetInfo::NativeToken { denom } => {
+            // transfer_from native token
+            pull_messages.push(native_pull_message(
+                ctx,
+                &requirement.approver,
+                denom,
+                amount,
+            )?);
+            vec![cosmwasm_std::Coin {
+                denom: denom.clone(),
+                amount,
+            }]
+        }
+    };
+
+    // Assume that the owner approve infinite allowance to the contract
+    let swap_message = match (&router_kind, &offer_asset) {
+        (RouterKind::MixedRouter, AssetInfo::Token { contract_addr }) => cw20_send_message(
+            ctx,
+            cw20_pull,
+            &requirement.approver,
+            contract_addr,
+            &router_addr,
+            amount,
+            to_json_binary(&Cw20RouterHookMsg::ExecuteSwapOperations {
+                operations,
+                minimum_receive,
+                to: Some(ctx.fees_receiver.to_string()),
+                affiliates: None,
+            })?,
+        )?,
+        (RouterKind::MixedRouter, AssetInfo::NativeToken { .. }) => {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: router_addr.to_string(),
+                msg: to_json_binary(&RouterExecuteMsg::ExecuteSwapOperations {
+                    operations,
+                    to: Some(ctx.fees_receiver.clone()),
+                    minimum_receive,
+                    affiliates: None,
+                })?,
+                funds,
+            })
+        }
+        (RouterKind::OraiswapRouter, AssetInfo::Token { contract_addr }) => cw20_send_message(
+            ctx,
+            cw20_pull,
+            &requirement.approver,
+            contract_addr,
+            &router_addr,
+            amount,
+            to_json_binary(&LegacyRouterHookMsg::ExecuteSwapOperations {
+                operations: legacy_operations(&operations),
+                minimum_receive,
+                to: Some(ctx.fees_receiver.to_string()),
+            })?,
+        )?,
+        (RouterKind::OraiswapRouter, AssetInfo::NativeToken { .. }) => {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: router_addr.to_string(),
+                msg: to_json_binary(&LegacyRouterExecuteMsg::ExecuteSwapOperations {
+                    operations: legacy_operations(&operations),
+                    minimum_receive,
+                    to: Some(ctx.fees_receiver.clone()),
+                })?,
+                funds,
+            })
+        }
+        (RouterKind::OraiswapV3, offer_asset) => {
+            let SwapOperation::SwapV3 { pool_key, x_to_y } = &operations[0] else {
+                return Err(CollectFeeSkipReason::UnsupportedSwapOperation);
+            };
+
+            // the pool contract pulls cw20 from its sender, so the fees are moved in first
+            if let AssetInfo::Token { contract_addr } = offer_asset {
+                pull_messages.extend([
+                    cw20_transfer_message(
+                        ctx,
+                        cw20_pull,
+                        &requirement.approver,
+                        contract_addr,
+                        amount,
+                    )?,
+                    CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr: contract_addr.clone().into(),
+                        msg: to_json_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                            spender: router_addr.to_string(),
+                            amount,
+                            expires: None,
+                        })?,
+                        funds: vec![],
+                    }),
+                ]);
+            }
+
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: router_addr.to_string(),
+                msg: to_json_binary(&V3ExecuteMsg::Swap {
+                    pool_key: pool_key.clone(),
+                    x_to_y: *x_to_y,
+                    amount: TokenAmount(amount.u128()),
+                    by_amount_in: true,
+                    sqrt_price_limit: if *x_to_y {
+                        SqrtPrice::new(MIN_SQRT_PRICE)
+                    } else {
+                        SqrtPrice::new(MAX_SQRT_PRICE)
+                    },
+                })?,
+                funds,
+            })
+        }
+    };
+
+    Ok(CollectFeeMessages {
+        offer_asset: offer_asset.clone(),
+        pull_messages,
+        swap_message,
+        // the pool contract has no minimum receive, it is checked once the swap settled
+        reply_minimum_receive: match router_kind {
+            RouterKind::OraiswapV3 => minimum_receive,
+            _ => None,
+        },
+        remainder,
+        fee_receiver,
+        // quoted whenever the executor has a value quota
+        value: simulated.unwrap_or_default(),
+    })
+}
+
+/// Returns the address of an allowed fee receiver, `None` when the contract keeps the proceeds.
+fn resolve_fee_receiver(
+    deps: Deps,
+    name: Option<&str>,
+) -> Result<Option<Addr>, CollectFeeSkipReason> {
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    FEE_RECEIVERS
+        .may_load(deps.storage, name)?
+        .map(Some)
+        .ok_or(CollectFeeSkipReason::UnknownFeeReceiver)
+}
+
+/// Only labels listed by the owner are aggregated, which keeps the revenue query bounded.
+fn check_label(deps: Deps, label: Option<&str>) -> Result<(), CollectFeeSkipReason> {
+    match label {
+        Some(label) if !REVENUE_LABELS.has(deps.storage, label) => {
+            Err(CollectFeeSkipReason::UnknownLabel)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Moves cw20 fees of the approver into the contract.
+fn cw20_transfer_message(
+    ctx: &CollectContext,
+    cw20_pull: &Cw20PullMode,
+    approver: &Addr,
+    token: &Addr,
+    amount: Uint128,
+) -> Result<CosmosMsg, CollectFeeSkipReason> {
+    match cw20_pull {
+        Cw20PullMode::Authz => cw20_authz_message(
+            ctx,
+            approver,
+            token,
+            &Cw20ExecuteMsg::Transfer {
+                recipient: ctx.fees_receiver.to_string(),
+                amount,
+            },
+        ),
+        Cw20PullMode::Allowance => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: approver.to_string(),
+                recipient: ctx.fees_receiver.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        })),
+    }
+}
+
+/// Sends cw20 fees of the approver to `contract` along with the hook `msg`.
+fn cw20_send_message(
+    ctx: &CollectContext,
+    cw20_pull: &Cw20PullMode,
+    approver: &Addr,
+    token: &Addr,
+    contract: &Addr,
+    amount: Uint128,
+    msg: Binary,
+) -> Result<CosmosMsg, CollectFeeSkipReason> {
+    match cw20_pull {
+        Cw20PullMode::Authz => cw20_authz_message(
+            ctx,
+            approver,
+            token,
+            &Cw20ExecuteMsg::Send {
+                contract: contract.to_string(),
+                amount,
+                msg,
+            },
+        ),
+        Cw20PullMode::Allowance => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::SendFrom {
+                owner: approver.to_string(),
+                contract: contract.to_string(),
+                amount,
+                msg,
+            })?,
+            funds: vec![],
+        })),
+    }
+}
+
+/// Executes `msg` on the cw20 token as the approver, through its authz `MsgExecuteContract` grant.
+fn cw20_authz_message(
+    ctx: &CollectContext,
+    approver: &Addr,
+    token: &Addr,
+    msg: &Cw20ExecuteMsg,
+) -> Result<CosmosMsg, CollectFeeSkipReason> {
+    let execute_any = Any {
+        type_url: MSG_EXECUTE_CONTRACT_TYPE_URL.to_string(),
+        value: MsgExecuteContract {
+            sender: approver.to_string(),
+            contract: token.to_string(),
+            msg: to_json_vec(msg)?,
+            funds: vec![],
+        }
+        .encode_to_vec(),
+    };
+
+    Ok(CosmosMsg::Stargate {
+        type_url: MSG_EXEC_TYPE_URL.to_string(),
+        value: Binary::from(
+            MsgExec {
+                grantee: ctx.fees_receiver.to_string(),
+                msgs: vec![execute_any],
+            }
+            .encode_to_vec(),
+        ),
+    })
+}
+
+/// Pulls native fees from the approver through its authz `MsgSend` grant.
+fn native_pull_message(
+    ctx: &CollectContext,
+    approver: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> Result<CosmosMsg, CollectFeeSkipReason> {
+    let send = MsgSend {
+        from_address: approver.to_string(),
+        to_address: ctx.fees_receiver.to_string(),
+        amount: vec![Coin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        }],
+    };
+    let send_any = send
+        .to_any()
+        .map_err(|err| CollectFeeSkipReason::EncodingError {
+            msg: err.to_string(),
+        })?;
+
+    let stargate_value = Binary::from(
+        MsgExec {
+            grantee: ctx.fees_receiver.to_string(),
+            msgs: vec![send_any],
+        }
+        .encode_to_vec(),
+    );
+
+    Ok(CosmosMsg::Stargate {
+        type_url: MSG_EXEC_TYPE_URL.to_string(),
+        value: stargate_value,
+    })
+}
+
+/// Resolves the router selected by a requirement. The default mixed router is used
+/// when none is selected or the selected one is paused.
+fn select_router(
+    deps: Deps,
+    ctx: &CollectContext,
+    name: Option<&str>,
+) -> Result<(Addr, RouterKind), CollectFeeSkipReason> {
+    if let Some(name) = name {
+        let router = ROUTERS
+            .may_load(deps.storage, name)?
+            .ok_or(CollectFeeSkipReason::UnknownRouter)?;
+        // the route was picked for this router, another one may price it very differently
+        if router.paused {
+            return Err(CollectFeeSkipReason::RouterPaused);
+        }
+        return Ok((router.addr, router.kind));
+    }
+    Ok((ctx.router.clone(), RouterKind::MixedRouter))
+}
+
+fn router_supports(kind: &RouterKind, operations: &[SwapOperation]) -> bool {
+    match kind {
+        RouterKind::MixedRouter => true,
+        RouterKind::OraiswapV3 => matches!(operations, [SwapOperation::SwapV3 { .. }]),
+        RouterKind::OraiswapRouter => operations
+            .iter()
+            .all(|operation| matches!(operation, SwapOperation::OraiSwap { .. })),
+    }
+}
+
+fn legacy_operations(operations: &[SwapOperation]) -> Vec<LegacySwapOperation> {
+    operations
+        .iter()
+        .filter_map(|operation| match operation {
+            SwapOperation::OraiSwap {
+                offer_asset_info,
+                ask_asset_info,
+            } => Some(LegacySwapOperation::OraiSwap {
+                offer_asset_info: offer_asset_info.clone(),
+                ask_asset_info: ask_asset_info.clone(),
+            }),
+            SwapOperation::SwapV3 { .. } => None,
+        })
+        .collect()
+}
+
+/// Returns how much of `offer_asset` the contract is allowed to pull from `approver`:
+/// the unexpired cw20 allowance, or the authz `MsgSend` and `MsgExecuteContract` grants
+/// when grant checks are enabled.
+fn query_granted_amount(
+    deps: Deps,
+    ctx: &CollectContext,
+    cw20_pull: &Cw20PullMode,
+    approver: &Addr,
+    offer_asset: &AssetInfo,
+) -> Result<Uint128, CollectFeeSkipReason> {
+    match offer_asset {
+        AssetInfo::Token { contract_addr } if *cw20_pull == Cw20PullMode::Authz => {
+            if !ctx.check_authz_grants {
+                return Ok(Uint128::MAX);
+            }
+
+            // execution authorizations limit the calls rather than the amount
+            let grants = query_authz_grants(
+                &deps.querier,
+                approver,
+                &ctx.fees_receiver,
+                MSG_EXECUTE_CONTRACT_TYPE_URL,
+            )
+            .map_err(|err| CollectFeeSkipReason::GrantQueryFailed {
+                msg: err.to_string(),
+            })?;
+
+            let granted = grants
+                .iter()
+                .filter(|grant| {
+                    grant.expiration.as_ref().map_or(true, |expiration| {
+                        expiration.seconds > ctx.block.time.seconds() as i64
+                    })
+                })
+                .filter_map(|grant| grant.authorization.as_ref())
+                .any(|authorization| match authorization.type_url.as_str() {
+                    GENERIC_AUTHORIZATION_TYPE_URL => true,
+                    CONTRACT_EXECUTION_AUTHORIZATION_TYPE_URL => {
+                        ContractExecutionAuthorization::decode(authorization.value.as_slice())
+                            .map(|execution_authorization| {
+                                execution_authorization
+                                    .grants
+                                    .iter()
+                                    .any(|grant| contract_grant_allows_pull(grant, contract_addr))
+                            })
+                            .unwrap_or_default()
+                    }
+                    _ => false,
+                });
+            Ok(match granted {
+                true => Uint128::MAX,
+                false => Uint128::zero(),
+            })
+        }
+        AssetInfo::Token { contract_addr } => {
+            let allowance: AllowanceResponse = deps
+                .querier
+                .query_wasm_smart(
+                    contract_addr,
+                    &Cw20QueryMsg::Allowance {
+                        owner: approver.to_string(),
+                        spender: ctx.fees_receiver.to_string(),
+                    },
+                )
+                .map_err(|err| CollectFeeSkipReason::GrantQueryFailed {
+                    msg: err.to_string(),
+                })?;
+
+            if allowance.expires.is_expired(&ctx.block) {
+                return Ok(Uint128::zero());
+            }
+            Ok(allowance.allowance)
+        }
+        AssetInfo::NativeToken { denom } => {
+            if !ctx.check_authz_grants {
+                return Ok(Uint128::MAX);
+            }
+
+            let grants = query_authz_grants(
+                &deps.querier,
+                approver,
+                &ctx.fees_receiver,
+                MSG_SEND_TYPE_URL,
+            )
+            .map_err(|err| CollectFeeSkipReason::GrantQueryFailed {
+                msg: err.to_string(),
+            })?;
+
+            let granted = grants
+                .iter()
+                .filter(|grant| {
+                    grant.expiration.as_ref().map_or(true, |expiration| {
+                        expiration.seconds > ctx.block.time.seconds() as i64
+                    })
+                })
+                .filter_map(|grant| grant.authorization.as_ref())
+                .map(|authorization| match authorization.type_url.as_str() {
+                    SEND_AUTHORIZATION_TYPE_URL => {
+                        SendAuthorization::decode(authorization.value.as_slice())
+                            .map(|send_authorization| {
+                                send_authorization
+                                    .spend_limit
+                                    .iter()
+                                    .find(|coin| coin.denom == *denom)
+                                    .and_then(|coin| coin.amount.parse::<u128>().ok())
+                                    .map(Uint128::from)
+                                    .unwrap_or_default()
+                            })
+                            .unwrap_or_default()
+                    }
+                    GENERIC_AUTHORIZATION_TYPE_URL => Uint128::MAX,
+                    _ => Uint128::zero(),
+                })
+                .max()
+                .unwrap_or_default();
+
+            Ok(granted)
+        }
+    }
+}
+
+/// Whether a contract grant lets the contract execute `Send` and `Transfer` on `token`
+/// at least once more. The pulls never attach funds, so only the call limits matter.
+fn contract_grant_allows_pull(grant: &ContractGrant, token: &Addr) -> bool {
+    if grant.contract != token.as_str() {
+        return false;
+    }
+
+    let calls_left = grant
+        .limit
+        .as_ref()
+        .map_or(false, |limit| match limit.type_url.as_str() {
+            MAX_CALLS_LIMIT_TYPE_URL => MaxCallsLimit::decode(limit.value.as_slice())
+                .map_or(false, |limit| limit.remaining > 0),
+            COMBINED_LIMIT_TYPE_URL => CombinedLimit::decode(limit.value.as_slice())
+                .map_or(false, |limit| limit.calls_remaining > 0),
+            MAX_FUNDS_LIMIT_TYPE_URL => true,
+            _ => false,
+        });
+    // the router decides between `Send` and `Transfer`, so a key filter must accept both
+    let accepted = grant
+        .filter
+        .as_ref()
+        .map_or(false, |filter| match filter.type_url.as_str() {
+            ALLOW_ALL_MESSAGES_FILTER_TYPE_URL => true,
+            ACCEPTED_MESSAGE_KEYS_FILTER_TYPE_URL => {
+                AcceptedMessageKeysFilter::decode(filter.value.as_slice()).map_or(false, |filter| {
+                    ["send", "transfer"]
+                        .iter()
+                        .all(|key| filter.keys.iter().any(|accepted| accepted == key))
+                })
+            }
+            _ => false,
+        });
+    calls_left && accepted
+}
+
+/// Quotes the distribute token received for `offer_amount` through the selected router.
+fn simulate_swap(
+    deps: Deps,
+    ctx: &CollectContext,
+    router_addr: &Addr,
+    router_kind: &RouterKind,
+    operations: &[SwapOperation],
+    offer_amount: Uint128,
+) -> Result<Uint128, CollectFeeSkipReason> {
+    let simulation: StdResult<SimulateSwapOperationsResponse> = match router_kind {
+        // the legacy router only knows its own operations
+        RouterKind::OraiswapRouter => deps.querier.query_wasm_smart(
+            router_addr,
+            &LegacyRouterQueryMsg::SimulateSwapOperations {
+                offer_amount,
+                operations: legacy_operations(operations),
+            },
+        ),
+        // a direct v3 swap is quoted by the mixed router
+        RouterKind::MixedRouter | RouterKind::OraiswapV3 => deps.querier.query_wasm_smart(
+            &ctx.router,
+            &RouterQueryMsg::SimulateSwapOperations {
+                offer_amount,
+                operations: operations.to_vec(),
+            },
+        ),
+    };
+
+    simulation
+        .map(|simulation| simulation.amount)
+        .map_err(|err| CollectFeeSkipReason::SimulationFailed {
+            msg: err.to_string(),
+        })
+}
+
+/// Returns the offer asset of the first hop and the ask asset of the last hop.
+fn resolve_swap_assets(
+    deps: Deps,
+    operations: &[SwapOperation],
+) -> Result<(AssetInfo, AssetInfo), CollectFeeSkipReason> {
+    let (Some(first), Some(last)) = (operations.first(), operations.last()) else {
+        return Err(CollectFeeSkipReason::EmptySwapOperations);
+    };
+
+    let offer_asset = match first {
+        SwapOperation::OraiSwap {
+            offer_asset_info, ..
+        } => offer_asset_info.clone(),
+        SwapOperation::SwapV3 { pool_key, x_to_y } => {
+            if *x_to_y {
+                resolve_pool_token(deps, &pool_key.token_x)?
+            } else {
+                resolve_pool_token(deps, &pool_key.token_y)?
+            }
+        }
+    };
+    Ok((offer_asset, resolve_ask_asset(deps, last)?))
+}
+
+/// Returns the ask asset of every hop of the route, in swap order.
+fn resolve_ask_assets(
+    deps: Deps,
+    operations: &[SwapOperation],
+) -> Result<Vec<AssetInfo>, CollectFeeSkipReason> {
+    operations
+        .iter()
+        .map(|operation| resolve_ask_asset(deps, operation))
+        .collect()
+}
+
+fn resolve_ask_asset(
+    deps: Deps,
+    operation: &SwapOperation,
+) -> Result<AssetInfo, CollectFeeSkipReason> {
+    match operation {
+        SwapOperation::OraiSwap { ask_asset_info, .. } => Ok(ask_asset_info.clone()),
+        SwapOperation::SwapV3 { pool_key, x_to_y } => {
+            if *x_to_y {
+                resolve_pool_token(deps, &pool_key.token_y)
+            } else {
+                resolve_pool_token(deps, &pool_key.token_x)
+            }
+        }
+    }
+}
+
+fn resolve_pool_token(deps: Deps, token: &str) -> Result<AssetInfo, CollectFeeSkipReason> {
+    asset_info_from_string(deps, token).map_err(|err| CollectFeeSkipReason::UnresolvedAsset {
+        msg: err.to_string(),
+    })
+}
+
+fn collect_fee_event(result: &CollectFeeRequirementResult) -> Event {
+    let status = match result.status {
+        CollectFeeStatus::Collected => "collected",
+        CollectFeeStatus::Claimed => "claimed",
+        CollectFeeStatus::Rejected => "rejected",
+        CollectFeeStatus::Skipped => "skipped",
+    };
+
+    // empty attribute values are rejected by wasmd, so optional fields are only added when set
+    let mut event = Event::new("collect_fee").add_attribute("approver", result.approver.as_str());
+    if let Some(offer_asset) = &result.offer_asset {
+        event = event.add_attribute("offer_asset", offer_asset.to_string());
+    }
+    event = event
+        .add_attribute("amount", result.amount.to_string())
+        .add_attribute("status", status);
+    if let Some(minimum_receive) = result.minimum_receive {
+        event = event.add_attribute("minimum_receive", minimum_receive.to_string());
+    }
+    if let Some(router) = &result.router {
+        event = event.add_attribute("router", router.as_str());
+    }
+    if let Some(skip_reason) = &result.skip_reason {
+        event = event.add_attribute("skip_reason", skip_reason.to_string());
+    }
+    event
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if let Some(requirement) = PENDING_CLAIMS.may_load(deps.storage, msg.id)? {
+        PENDING_CLAIMS.remove(deps.storage, msg.id);
+        return match msg.result {
+            SubMsgResult::Ok(_) => reply_claim(deps, env, requirement),
+            SubMsgResult::Err(error) => {
+                let config = CONFIG.load(deps.storage)?;
+                let mut response = Response::new();
+                if let Ok((offer_asset, _)) =
+                    resolve_swap_assets(deps.as_ref(), &requirement.swap_operations)
+                {
+                    response = record_failed_collection(
+                        deps.storage,
+                        &env,
+                        msg.id,
+                        requirement,
+                        offer_asset,
+                        Uint128::zero(),
+                        error,
+                    )?;
+                }
+                settle_collection(deps, config, response, Uint128::zero())
+            }
+        };
+    }
+
+    let pending = PENDING_COLLECTIONS.load(deps.storage, msg.id)?;
+    PENDING_COLLECTIONS.remove(deps.storage, msg.id);
+
+    let config = CONFIG.load(deps.storage)?;
+    if let SubMsgResult::Err(error) = msg.result {
+        // the pulls were reverted with the swap, the fees are still in the approver account
+        let response = record_failed_collection(
+            deps.storage,
+            &env,
+            msg.id,
+            pending.requirement,
+            pending.offer_asset,
+            pending.offer_amount,
+            error,
+        )?;
+        return settle_collection(deps, config, response, Uint128::zero());
+    }
+    let distribute_asset_info =
+        asset_info_from_string(deps.as_ref(), config.distribute_token.as_str())?;
+    let balance = distribute_asset_info.query_pool(&deps.querier, env.contract.address.clone())?;
+    let snapshot = COLLECTION_SNAPSHOT.load(deps.storage)?;
+    let received = balance.checked_sub(snapshot).unwrap_or_default();
+    // forwarded proceeds leave the contract before the next swap is measured
+    let kept = match pending.fee_receiver {
+        Some(_) => Uint128::zero(),
+        None => received,
+    };
+    COLLECTION_SNAPSHOT.save(deps.storage, &(balance - (received - kept)))?;
+
+    let run = COLLECTION_RUN.load(deps.storage)?;
+    let below_minimum_receive = pending
+        .minimum_receive
+        .filter(|minimum_receive| received < *minimum_receive);
+    if let Some(minimum_receive) = below_minimum_receive {
+        if run.mode == CollectMode::Atomic {
+            return Err(ContractError::BelowMinimumReceive {
+                collection_id: msg.id,
+                received,
+                minimum_receive,
+            });
+        }
+    }
+
+    // permissionless keepers have no stats
+    let executor = run.executor;
+    if let Some(mut stats) = EXECUTOR_STATS.may_load(deps.storage, &executor)? {
+        stats.total_proceeds += received;
+        EXECUTOR_STATS.save(deps.storage, &executor, &stats)?;
+    }
+
+    let label = pending.requirement.label;
+    if let Some(label) = &label {
+        let epoch = env.block.time.seconds() / REVENUE_EPOCH;
+        REVENUE_BY_SOURCE.update(
+            deps.storage,
+            (epoch, label.as_str()),
+            |revenue| -> StdResult<_> { Ok(revenue.unwrap_or_default() + received) },
+        )?;
+    }
+
+    // a best-effort swap below its minimum can not be undone anymore, its proceeds are
+    // handled like any others while the requirement is queued to be retried
+    let mut retry = None;
+    let error = match below_minimum_receive {
+        Some(minimum_receive) => {
+            let error = ContractError::BelowMinimumReceive {
+                collection_id: msg.id,
+                received,
+                minimum_receive,
+            }
+            .to_string();
+            retry = Some(queue_failed_collection(
+                deps.storage,
+                &env,
+                msg.id,
+                &pending.requirement,
+                &pending.offer_asset,
+                &error,
+            )?);
+            Some(error)
+        }
+        None => {
+            // a requirement collected again leaves the retry queue
+            if let Some((id, _)) = find_failed_collection(deps.storage, &pending.requirement)? {
+                FAILED_COLLECTIONS.remove(deps.storage, (&pending.requirement.approver, id));
+            }
+            None
+        }
+    };
+
+    let record = CollectionRecord {
+        id: msg.id,
+        approver: pending.requirement.approver,
+        offer_asset: pending.offer_asset,
+        offer_amount: pending.offer_amount,
+        received,
+        height: env.block.height,
+        time: env.block.time,
+        error,
+        fee_receiver: pending.fee_receiver,
+    };
+    COLLECTION_HISTORY.save(deps.storage, msg.id, &record)?;
+
+    let mut event = Event::new("collection_proceeds")
+        .add_attribute("collection_id", record.id.to_string())
+        .add_attribute("approver", record.approver.as_str())
+        .add_attribute("offer_asset", record.offer_asset.to_string())
+        .add_attribute("offer_amount", record.offer_amount.to_string())
+        .add_attribute("received", record.received.to_string());
+    if let Some(label) = label {
+        event = event.add_attribute("label", label);
+    }
+    if let (Some(minimum_receive), Some((attempts, dropped))) = (below_minimum_receive, retry) {
+        event = event
+            .add_attribute("below_minimum_receive", minimum_receive.to_string())
+            .add_attribute("attempts", attempts.to_string())
+            .add_attribute("dropped", dropped.to_string());
+    }
+    let mut response = Response::new();
+    if let Some(fee_receiver) = &record.fee_receiver {
+        event = event.add_attribute("fee_receiver", fee_receiver.as_str());
+        if !received.is_zero() {
+            response = response.add_message(transfer_message(
+                &distribute_asset_info,
+                fee_receiver,
+                received,
+            )?);
+        }
+    }
+    let response = response.add_event(event);
+
+    // only the proceeds kept by the contract are distributed and pay the keeper
+    settle_collection(deps, config, response, kept)
+}
+
+/// Records a best-effort collection that failed, nothing was received for it.
+fn record_failed_collection(
+    storage: &mut dyn Storage,
+    env: &Env,
+    collection_id: u64,
+    requirement: CollectFeeRequirement,
+    offer_asset: AssetInfo,
+    offer_amount: Uint128,
+    error: String,
+) -> StdResult<Response> {
+    let (attempts, dropped) = queue_failed_collection(
+        storage,
+        env,
+        collection_id,
+        &requirement,
+        &offer_asset,
+        &error,
+    )?;
+
+    let record = CollectionRecord {
+        id: collection_id,
+        approver: requirement.approver,
+        offer_asset,
+        offer_amount,
+        received: Uint128::zero(),
+        height: env.block.height,
+        time: env.block.time,
+        error: Some(error),
+        fee_receiver: None,
+    };
+    COLLECTION_HISTORY.save(storage, collection_id, &record)?;
+
+    Ok(Response::new().add_event(
+        Event::new("collection_failed")
+            .add_attribute("collection_id", record.id.to_string())
+            .add_attribute("approver", record.approver.as_str())
+            .add_attribute("offer_asset", record.offer_asset.to_string())
+            .add_attribute("offer_amount", record.offer_amount.to_string())
+            .add_attribute("attempts", attempts.to_string())
+            .add_attribute("dropped", dropped.to_string())
+            .add_attribute("error", record.error.unwrap_or_default()),
+    ))
+}
+
+/// Queues a failed requirement to be retried, dropping it after `MAX_COLLECTION_ATTEMPTS`.
+/// Returns its attempts and whether it was dropped.
+fn queue_failed_collection(
+    storage: &mut dyn Storage,
+    env: &Env,
+    collection_id: u64,
+    requirement: &CollectFeeRequirement,
+    offer_asset: &AssetInfo,
+    error: &str,
+) -> StdResult<(u32, bool)> {
+    let (id, attempts) = match find_failed_collection(storage, requirement)? {
+        Some((id, failed_collection)) => (id, failed_collection.attempts + 1),
+        None => (collection_id, 1),
+    };
+    let failed_key = (&requirement.approver, id);
+    let dropped = attempts >= MAX_COLLECTION_ATTEMPTS;
+    if dropped {
+        FAILED_COLLECTIONS.remove(storage, failed_key);
+    } else {
+        FAILED_COLLECTIONS.save(
+            storage,
+            failed_key,
+            &FailedCollection {
+                id,
+                requirement: requirement.clone(),
+                offer_asset: offer_asset.clone(),
+                error: error.to_string(),
+                attempts,
+                last_try_height: env.block.height,
+                last_try_time: env.block.time,
+            },
+        )?;
+    }
+    Ok((attempts, dropped))
+}
+
+/// Returns the failed collection recorded for the same requirement of its approver.
+fn find_failed_collection(
+    storage: &dyn Storage,
+    requirement: &CollectFeeRequirement,
+) -> StdResult<Option<(u64, FailedCollection)>> {
+    FAILED_COLLECTIONS
+        .prefix(&requirement.approver)
+        .range(storage, None, None, Order::Ascending)
+        .find(|item| {
+            item.as_ref().map_or(true, |(_, failed_collection)| {
+                failed_collection.requirement == *requirement
+            })
+        })
+        .transpose()
+}
+
+/// Collects a requirement once its fees were claimed into the approver account.
+fn reply_claim(
+    deps: DepsMut,
+    env: Env,
+    requirement: CollectFeeRequirement,
+) -> Result<Response, ContractError> {
+    // the fees are claimed, should the swap fail only the swap is retried
+    let requirement = CollectFeeRequirement {
+        source: None,
+        ..requirement
+    };
+    let config = CONFIG.load(deps.storage)?;
+    let mut run = COLLECTION_RUN.load(deps.storage)?;
+    let ctx = collect_context(deps.as_ref(), env, &config, &run.executor, None)?;
+    // the approver was checked before claiming, and its cooldown has already started
+    let settings = APPROVER_SETTINGS
+        .may_load(deps.storage, &requirement.approver)?
+        .unwrap_or_default();
+
+    let mut result = new_result(&requirement.approver);
+    // requirements queued after the claim have not pulled yet, so everything the run queued
+    // stays reserved. This also keeps the cap of the approver across the whole run, the
+    // pulls before the claim already ran and can only leave some fees behind
+    let taken = run
+        .taken
+        .iter()
+        .map(|(approver, asset_key, amount)| ((approver.clone(), asset_key.clone()), *amount))
+        .collect::<BTreeMap<_, _>>();
+    match build_collect_fee_messages(
+        deps.as_ref(),
+        &ctx,
+        &requirement,
+        &settings,
+        &taken,
+        &mut result,
+    ) {
+        Ok(approver_messages) => {
+            // later claims of the run reserve the amount of this one
+            let asset_key = approver_messages.offer_asset.to_string();
+            match run
+                .taken
+                .iter_mut()
+                .find(|(approver, key, _)| *approver == requirement.approver && *key == asset_key)
+            {
+                Some((_, _, amount)) => *amount += result.amount,
+                None => run
+                    .taken
+                    .push((requirement.approver.clone(), asset_key, result.amount)),
+            }
+            COLLECTION_RUN.save(deps.storage, &run)?;
+
+            // the swap takes the place of the claim in the pending count of the run
+            let collection_id = COLLECTION_COUNT.load(deps.storage)? + 1;
+            COLLECTION_COUNT.save(deps.storage, &collection_id)?;
+            result.collection_id = Some(collection_id);
+
+            consume_executor_value(
+                deps.storage,
+                &ctx.block,
+                &run.executor,
+                approver_messages.value,
+            )?;
+            let messages = queue_collection(
+                deps.storage,
+                &ctx,
+                collection_id,
+                &requirement,
+                &result,
+                approver_messages,
+                &run.mode,
+            )?;
+            Ok(Response::new()
+                .add_event(collect_fee_event(&result))
+                .add_submessages(messages))
+        }
+        Err(reason) => {
+            result.status = skipped_status(&reason);
+            result.skip_reason = Some(reason);
+
+            let response = Response::new().add_event(collect_fee_event(&result));
+            settle_collection(deps, config, response, Uint128::zero())
+        }
+    }
+}
+
+/// Accounts a settled step of the collection run, finishing the run after its last step.
+fn settle_collection(
+    deps: DepsMut,
+    config: Config,
+    mut response: Response,
+    received: Uint128,
+) -> Result<Response, ContractError> {
+    let mut run = COLLECTION_RUN.load(deps.storage)?;
+    run.pending -= 1;
+    run.received += received;
+    if run.pending > 0 {
+        COLLECTION_RUN.save(deps.storage, &run)?;
+        return Ok(response);
+    }
 
-    let mut response = Response::new();
+    // every swap of the run has settled
+    COLLECTION_RUN.remove(deps.storage);
+    let distribute_asset_info =
+        asset_info_from_string(deps.as_ref(), config.distribute_token.as_str())?;
+    response = response
+        .add_attribute("action", "collection_settled")
+        .add_attribute("received", run.received.to_string());
+
+    let mut amount_distribute = run.received;
+    if let Some(keeper_bounty) = &config.keeper_bounty {
+        let bounty = (run.received * Decimal::from_ratio(keeper_bounty.bps, MAX_BPS))
+            .min(keeper_bounty.max_per_call);
+        if !bounty.is_zero() {
+            amount_distribute -= bounty;
+            response = response
+                .add_message(transfer_message(
+                    &distribute_asset_info,
+                    &run.executor,
+                    bounty,
+                )?)
+                .add_attribute("keeper", run.executor.as_str())
+                .add_attribute("keeper_bounty", bounty.to_string());
+        }
+    }
 
-    if !messages.is_empty() {
-        response = response.add_messages(messages);
+    if run.auto_distribute && !amount_distribute.is_zero() {
+        let messages =
+            _load_target_messages(deps.storage, amount_distribute, &distribute_asset_info)?;
+        response = response
+            .add_messages(messages)
+            .add_attribute("amount_distribute", amount_distribute.to_string());
     }
 
     Ok(response)
 }
 
+/// Sends `amount` of the distribute token held by the contract to `recipient`.
+fn transfer_message(
+    distribute_asset_info: &AssetInfo,
+    recipient: &Addr,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    Ok(match distribute_asset_info {
+        AssetInfo::Token { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }),
+        AssetInfo::NativeToken { denom } => CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(amount.u128(), denom),
+        }),
+    })
+}
+
 fn _load_target_messages(
     storage: &mut dyn Storage,
     amount_distribute: Uint128,
-    distribute_token: Addr,
-) -> Result<Vec<WasmMsg>, ContractError> {
+    distribute_asset_info: &AssetInfo,
+) -> Result<Vec<CosmosMsg>, ContractError> {
     DISTRIBUTION_TARGETS
         .load(storage)?
         .iter()
-        .map(|target| -> Result<WasmMsg, ContractError> {
+        .map(|target| -> Result<CosmosMsg, ContractError> {
             let transfer_amount = amount_distribute * Decimal::percent(target.weight as u64);
 
-            let msg = match target.clone().msg_hook {
-                None => WasmMsg::Execute {
-                    contract_addr: distribute_token.clone().into(),
-                    msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-                        recipient: target.clone().addr.into(),
-                        amount: transfer_amount,
-                    })?,
-                    funds: vec![],
-                },
-                Some(msg_hook) => WasmMsg::Execute {
-                    contract_addr: distribute_token.clone().into(),
-                    msg: to_json_binary(&Cw20ExecuteMsg::Send {
-                        contract: target.clone().addr.into(),
-                        amount: transfer_amount,
+            // a native distribute token is attached to the hook instead of a cw20 send
+            let msg = match (target.clone().msg_hook, distribute_asset_info) {
+                (None, _) => {
+                    transfer_message(distribute_asset_info, &target.addr, transfer_amount)?
+                }
+                (Some(msg_hook), AssetInfo::Token { contract_addr }) => {
+                    CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr: contract_addr.to_string(),
+                        msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                            contract: target.clone().addr.into(),
+                            amount: transfer_amount,
+                            msg: msg_hook,
+                        })?,
+                        funds: vec![],
+                    })
+                }
+                (Some(msg_hook), AssetInfo::NativeToken { denom }) => {
+                    CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr: target.clone().addr.into(),
                         msg: msg_hook,
-                    })?,
-                    funds: vec![],
-                },
+                        funds: coins(transfer_amount.u128(), denom),
+                    })
+                }
             };
             Ok(msg)
         })
-        .collect::<Result<Vec<WasmMsg>, ContractError>>()
+        .collect::<Result<Vec<CosmosMsg>, ContractError>>()
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_json_binary(&ConfigResponse(CONFIG.load(deps.storage)?)),
         QueryMsg::DistributeTargets {} => to_json_binary(&DistributeTargetsResponse(
@@ -421,7 +2967,226 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::IsExecutor { addr } => {
             to_json_binary(&EXECUTORS.load(deps.storage, &addr).unwrap_or_default())
         }
+        QueryMsg::ExecutorQuota { executor } => {
+            to_json_binary(&query_executor_quota(deps, env, executor)?)
+        }
+        QueryMsg::ExecutorStats { addr } => to_json_binary(&ExecutorStatsResponse(
+            EXECUTOR_STATS.may_load(deps.storage, &addr)?,
+        )),
+        QueryMsg::AllExecutorStats { start_after, limit } => to_json_binary(
+            &AllExecutorStatsResponse(query_all_executor_stats(deps, start_after, limit)?),
+        ),
+        QueryMsg::RegisteredApprover { addr } => to_json_binary(&RegisteredApproverResponse(
+            REGISTERED_APPROVERS.may_load(deps.storage, &addr)?,
+        )),
+        QueryMsg::CollectionHistory { start_after, limit } => to_json_binary(
+            &CollectionHistoryResponse(query_collection_history(deps, start_after, limit)?),
+        ),
+        QueryMsg::Retentions { approver } => to_json_binary(&RetentionsResponse(
+            RETENTIONS
+                .prefix(&deps.api.addr_validate(approver.as_str())?)
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, retention)| retention))
+                .collect::<StdResult<Vec<AssetRetention>>>()?,
+        )),
+        QueryMsg::NativeReserves {} => to_json_binary(&NativeReservesResponse(
+            NATIVE_RESERVES
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, reserve)| reserve))
+                .collect::<StdResult<Vec<NativeReserve>>>()?,
+        )),
+        QueryMsg::FailedCollections { start_after, limit } => to_json_binary(
+            &FailedCollectionsResponse(query_failed_collections(deps, start_after, limit)?),
+        ),
+        QueryMsg::FeeReceivers {} => to_json_binary(&FeeReceiversResponse(
+            FEE_RECEIVERS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| {
+                    item.map(|(name, addr)| FeeReceiver {
+                        name,
+                        addr: Some(addr),
+                    })
+                })
+                .collect::<StdResult<Vec<FeeReceiver>>>()?,
+        )),
+        QueryMsg::AssetRegistry {} => to_json_binary(&AssetRegistryResponse(
+            ASSET_REGISTRY
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| {
+                    item.map(|(asset, asset_info)| AssetRegistryEntry {
+                        asset,
+                        asset_info: Some(asset_info),
+                    })
+                })
+                .collect::<StdResult<Vec<AssetRegistryEntry>>>()?,
+        )),
+        QueryMsg::RevenueBySource { from, to } => to_json_binary(&RevenueBySourceResponse(
+            query_revenue_by_source(deps, from, to)?,
+        )),
+        QueryMsg::RevenueLabels {} => to_json_binary(&RevenueLabelsResponse(
+            REVENUE_LABELS
+                .keys(deps.storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<String>>>()?,
+        )),
+        QueryMsg::AllowedOfferAssets {} => to_json_binary(&OfferAssetsResponse(
+            query_offer_asset_list(deps, ALLOWED_OFFER_ASSETS)?,
+        )),
+        QueryMsg::DeniedOfferAssets {} => to_json_binary(&OfferAssetsResponse(
+            query_offer_asset_list(deps, DENIED_OFFER_ASSETS)?,
+        )),
+        QueryMsg::CollectThresholds {} => to_json_binary(&CollectThresholdsResponse(
+            COLLECT_THRESHOLDS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, threshold)| threshold))
+                .collect::<StdResult<Vec<CollectThreshold>>>()?,
+        )),
+        QueryMsg::ChunkLimits {} => to_json_binary(&ChunkLimitsResponse(
+            CHUNK_LIMITS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, limit)| limit))
+                .collect::<StdResult<Vec<ChunkLimit>>>()?,
+        )),
+        QueryMsg::QueuedRemainder {
+            approver,
+            asset_info,
+        } => to_json_binary(&QueuedRemainderResponse(
+            QUEUED_REMAINDERS
+                .may_load(deps.storage, (&asset_info.to_string(), &approver))?
+                .unwrap_or_default(),
+        )),
+        QueryMsg::Routers {} => to_json_binary(&RoutersResponse(
+            ROUTERS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, router)| router))
+                .collect::<StdResult<Vec<RouterInfo>>>()?,
+        )),
+        QueryMsg::ApproverSettings { addr } => to_json_binary(&ApproverSettingsResponse {
+            settings: APPROVER_SETTINGS.may_load(deps.storage, &addr)?,
+            last_collected: LAST_COLLECTED.may_load(deps.storage, &addr)?,
+        }),
+        QueryMsg::RegisteredApprovers { start_after, limit } => to_json_binary(
+            &RegisteredApproversResponse(query_registered_approvers(deps, start_after, limit)?),
+        ),
+    }
+}
+
+fn query_executor_quota(deps: Deps, env: Env, executor: Addr) -> StdResult<ExecutorQuotaResponse> {
+    let Some(quota) = EXECUTOR_QUOTAS.may_load(deps.storage, &executor)? else {
+        return Ok(ExecutorQuotaResponse {
+            quota: None,
+            remaining_calls: None,
+            remaining_value: None,
+            window_end: None,
+        });
+    };
+
+    let usage = executor_usage(deps.storage, &env.block, &executor, &quota)?;
+    Ok(ExecutorQuotaResponse {
+        remaining_calls: quota
+            .max_calls
+            .map(|max_calls| max_calls.saturating_sub(usage.calls)),
+        remaining_value: quota
+            .max_value
+            .map(|max_value| max_value.saturating_sub(usage.value)),
+        window_end: (usage.calls > 0).then(|| usage.window_start.plus_seconds(quota.window)),
+        quota: Some(quota),
+    })
+}
+
+fn query_all_executor_stats(
+    deps: Deps,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ExecutorStats>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    EXECUTOR_STATS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, stats)| stats))
+        .collect()
+}
+
+fn query_revenue_by_source(
+    deps: Deps,
+    from: Timestamp,
+    to: Timestamp,
+) -> StdResult<Vec<SourceRevenue>> {
+    let (from, to) = (from.seconds() / REVENUE_EPOCH, to.seconds() / REVENUE_EPOCH);
+    if to.saturating_sub(from) >= MAX_REVENUE_EPOCHS {
+        return Err(StdError::generic_err(format!(
+            "Revenue can be queried for at most {} epochs",
+            MAX_REVENUE_EPOCHS
+        )));
+    }
+    let min = PrefixBound::inclusive(from);
+    let max = PrefixBound::inclusive(to);
+
+    let mut revenues: BTreeMap<String, Uint128> = BTreeMap::new();
+    for item in REVENUE_BY_SOURCE.prefix_range(deps.storage, Some(min), Some(max), Order::Ascending)
+    {
+        let ((_, label), amount) = item?;
+        *revenues.entry(label).or_default() += amount;
     }
+
+    Ok(revenues
+        .into_iter()
+        .map(|(label, amount)| SourceRevenue { label, amount })
+        .collect())
+}
+
+fn query_collection_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<CollectionRecord>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    COLLECTION_HISTORY
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, record)| record))
+        .collect()
+}
+
+fn query_failed_collections(
+    deps: Deps,
+    start_after: Option<(Addr, u64)>,
+    limit: Option<u32>,
+) -> StdResult<Vec<FailedCollection>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .as_ref()
+        .map(|(approver, id)| Bound::exclusive((approver, *id)));
+
+    FAILED_COLLECTIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, failed_collection)| failed_collection))
+        .collect()
+}
+
+fn query_offer_asset_list(deps: Deps, list: Map<&str, AssetInfo>) -> StdResult<Vec<AssetInfo>> {
+    list.range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, asset)| asset))
+        .collect()
+}
+
+fn query_registered_approvers(
+    deps: Deps,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<RegisteredApprover>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    REGISTERED_APPROVERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, registered_approver)| registered_approver))
+        .collect()
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -429,22 +3194,536 @@ pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, Co
     let mut config = CONFIG.load(deps.storage)?;
     config.router = Some(msg.new_router);
     CONFIG.save(deps.storage, &config)?;
+
+    // keep the previously hard-coded orai reserve for contracts migrating from older versions,
+    // a reserve removed by the owner afterwards must stay removed
+    let previous_version = get_contract_version(deps.storage)?.version;
+    if parse_version(&previous_version) < NATIVE_RESERVES_VERSION {
+        save_default_native_reserve(deps.storage)?;
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     Ok(Response::default())
 }
 
+/// Parses a `major.minor.patch` version, missing or invalid parts count as zero.
+fn parse_version(version: &str) -> [u64; 3] {
+    let mut parts = version
+        .split(|c: char| c == '.' || c == '-' || c == '+')
+        .map(|part| part.parse().unwrap_or_default());
+    [
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    ]
+}
+
+fn save_default_native_reserve(storage: &mut dyn Storage) -> StdResult<()> {
+    NATIVE_RESERVES.save(
+        storage,
+        DEFAULT_NATIVE_RESERVE_DENOM,
+        &NativeReserve {
+            denom: DEFAULT_NATIVE_RESERVE_DENOM.to_string(),
+            amount: Uint128::from(DEFAULT_NATIVE_RESERVE_AMOUNT),
+            percent: None,
+        },
+    )
+}
+
 #[cfg(test)]
 mod tests {
+    use std::marker::PhantomData;
+
+    use crate::helpers::AUTHZ_GRANTS_QUERY_PATH;
     use crate::msg::{ConfigResponse, DistributeTargetsResponse, InstantiateMsg, QueryMsg};
     use crate::state::{Config, DistributeTarget};
+    use cosmos_sdk_proto::cosmos::authz::v1beta1::{Grant, QueryGrantsResponse};
+    use cosmos_sdk_proto::cosmwasm::wasm::v1::AllowAllMessagesFilter;
+    use cosmos_sdk_proto::Any;
     use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
-    use cosmwasm_std::{from_json, OwnedDeps, Uint128};
+    use cosmwasm_std::{
+        coin, from_json, ContractInfoResponse, ContractResult, Empty, OwnedDeps, Querier,
+        QuerierResult, QueryRequest, SubMsgResponse, SystemError, SystemResult, Uint128, WasmQuery,
+    };
     use cosmwasm_std::{
         testing::{mock_dependencies, mock_env, mock_info},
         Addr,
     };
+    use cw20::BalanceResponse;
+    use oraiswap_v3::percentage::Percentage;
+    use oraiswap_v3::{FeeTier, PoolKey};
 
     use super::*;
 
+    /// Answers the authz grants stargate query, which `MockQuerier` does not support
+    struct AuthzQuerier {
+        base: MockQuerier,
+        grants: Vec<Grant>,
+    }
+
+    impl Querier for AuthzQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            match from_json::<QueryRequest<Empty>>(bin_request) {
+                Ok(QueryRequest::Stargate { path, .. }) if path == AUTHZ_GRANTS_QUERY_PATH => {
+                    SystemResult::Ok(ContractResult::Ok(Binary::from(
+                        QueryGrantsResponse {
+                            grants: self.grants.clone(),
+                            pagination: None,
+                        }
+                        .encode_to_vec(),
+                    )))
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    fn _authz_deps(grants: Vec<Grant>) -> OwnedDeps<MockStorage, MockApi, AuthzQuerier> {
+        let mut base = MockQuerier::new(&[("approver", &[coin(10000000, "orai")])]);
+        base.update_wasm(|query| match query {
+            // only the cw20 tokens are contracts, "orai" resolves to the native denom
+            WasmQuery::ContractInfo { contract_addr } => match contract_addr.as_str() {
+                "cw20_token" | "distribute_token" => SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&ContractInfoResponse::new(1, "creator")).unwrap(),
+                )),
+                _ => SystemResult::Err(SystemError::NoSuchContract {
+                    addr: contract_addr.clone(),
+                }),
+            },
+            // 1000 cw20 fees of the approver, and an empty distribute token snapshot
+            // taken before the swaps
+            WasmQuery::Smart { contract_addr, .. } => SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&BalanceResponse {
+                    balance: match contract_addr.as_str() {
+                        "cw20_token" => Uint128::from(1000u128),
+                        _ => Uint128::zero(),
+                    },
+                })
+                .unwrap(),
+            )),
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "wasm".to_string(),
+            }),
+        });
+
+        let mut deps = OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: AuthzQuerier { base, grants },
+            custom_query_type: PhantomData::<Empty>,
+        };
+
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                distribute_token: Addr::unchecked("distribute_token"),
+                init_distribution_targets: vec![],
+                router: Some(Addr::unchecked("router")),
+                executors: vec![Addr::unchecked("executor")],
+            },
+        )
+        .unwrap();
+        deps
+    }
+
+    fn _collect_native_fees_with_grants(grants: Vec<Grant>, count: usize) -> CollectFeesResult {
+        let mut deps = _authz_deps(grants);
+        let requirement = CollectFeeRequirement {
+            approver: Addr::unchecked("approver"),
+            swap_operations: vec![SwapOperation::SwapV3 {
+                pool_key: PoolKey {
+                    token_x: "orai".into(),
+                    token_y: "distribute_token".into(),
+                    fee_tier: FeeTier {
+                        fee: Percentage(3u64),
+                        tick_spacing: 100,
+                    },
+                },
+                x_to_y: true,
+            }],
+            minimum_receive: None,
+            router: None,
+            source: None,
+            fee_receiver: None,
+            label: None,
+        };
+        let res = execute_collect_fees(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("executor", &[]),
+            vec![requirement; count],
+            false,
+            CollectMode::Atomic,
+        )
+        .unwrap();
+
+        from_json::<CollectFeesResult>(&res.data.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_collect_native_fees_limited_by_authz_grant() {
+        let spend_limit = Grant {
+            authorization: Some(Any {
+                type_url: SEND_AUTHORIZATION_TYPE_URL.to_string(),
+                value: SendAuthorization {
+                    spend_limit: vec![Coin {
+                        denom: "orai".to_string(),
+                        amount: "500".to_string(),
+                    }],
+                }
+                .encode_to_vec(),
+            }),
+            expiration: None,
+        };
+        let spend_limited = _collect_native_fees_with_grants(vec![spend_limit.clone()], 1);
+        assert_eq!(
+            spend_limited.requirements[0].status,
+            CollectFeeStatus::Collected
+        );
+        assert_eq!(spend_limited.requirements[0].amount, Uint128::from(500u128));
+
+        // the spend limit is shared by every requirement of the batch
+        let spend_limited = _collect_native_fees_with_grants(vec![spend_limit], 2);
+        assert_eq!(spend_limited.requirements[0].amount, Uint128::from(500u128));
+        assert_eq!(
+            spend_limited.requirements[1].skip_reason,
+            Some(CollectFeeSkipReason::NoAuthzGrant)
+        );
+
+        let not_granted = _collect_native_fees_with_grants(vec![], 1);
+        assert_eq!(
+            not_granted.requirements[0].skip_reason,
+            Some(CollectFeeSkipReason::NoAuthzGrant)
+        );
+    }
+
+    #[test]
+    fn test_best_effort_failed_pull_keeps_treasury_funds() {
+        let mut deps = _authz_deps(vec![Grant {
+            authorization: Some(Any {
+                type_url: SEND_AUTHORIZATION_TYPE_URL.to_string(),
+                value: SendAuthorization {
+                    spend_limit: vec![Coin {
+                        denom: "orai".to_string(),
+                        amount: "500".to_string(),
+                    }],
+                }
+                .encode_to_vec(),
+            }),
+            expiration: None,
+        }]);
+        let contract = mock_env().contract.address;
+        deps.querier
+            .base
+            .update_balance(contract.clone(), vec![coin(5000, "orai")]);
+
+        let res = execute_collect_fees(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("executor", &[]),
+            vec![CollectFeeRequirement {
+                approver: Addr::unchecked("approver"),
+                swap_operations: vec![SwapOperation::SwapV3 {
+                    pool_key: PoolKey {
+                        token_x: "orai".into(),
+                        token_y: "distribute_token".into(),
+                        fee_tier: FeeTier {
+                            fee: Percentage(3u64),
+                            tick_spacing: 100,
+                        },
+                    },
+                    x_to_y: true,
+                }],
+                minimum_receive: None,
+                router: None,
+                source: None,
+                fee_receiver: None,
+                label: None,
+            }],
+            false,
+            CollectMode::BestEffort,
+        )
+        .unwrap();
+
+        // the pull and the swap run inside a single sub message of the contract
+        assert_eq!(res.messages.len(), 1);
+        let collection_id = res.messages[0].id;
+        assert_eq!(res.messages[0].reply_on, ReplyOn::Always);
+        let CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr, msg, ..
+        }) = &res.messages[0].msg
+        else {
+            panic!("expected a run collection message");
+        };
+        assert_eq!(contract_addr, contract.as_str());
+        let ExecuteMsg::RunCollection { messages } = from_json::<ExecuteMsg>(msg).unwrap() else {
+            panic!("expected a run collection message");
+        };
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(
+            &messages[0],
+            CosmosMsg::Stargate { type_url, .. } if type_url == MSG_EXEC_TYPE_URL
+        ));
+
+        // only the contract itself may run the grouped messages
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("executor", &[]),
+            ExecuteMsg::RunCollection {
+                messages: messages.clone(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // the authz pull fails, which reverts the swap on the orai held by the contract
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: collection_id,
+                result: SubMsgResult::Err("authz pull failed".to_string()),
+            },
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+
+        let record = COLLECTION_HISTORY
+            .load(deps.as_ref().storage, collection_id)
+            .unwrap();
+        assert_eq!(record.received, Uint128::zero());
+        assert_eq!(record.error, Some("authz pull failed".to_string()));
+        let failed = FAILED_COLLECTIONS
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].1.error, "authz pull failed");
+    }
+
+    #[test]
+    fn test_best_effort_v3_swap_below_minimum_receive() {
+        let mut deps = _authz_deps(vec![Grant {
+            authorization: Some(Any {
+                type_url: GENERIC_AUTHORIZATION_TYPE_URL.to_string(),
+                value: vec![],
+            }),
+            expiration: None,
+        }]);
+        let requirement = CollectFeeRequirement {
+            approver: Addr::unchecked("approver"),
+            swap_operations: vec![SwapOperation::SwapV3 {
+                pool_key: PoolKey {
+                    token_x: "orai".into(),
+                    token_y: "distribute_token".into(),
+                    fee_tier: FeeTier {
+                        fee: Percentage(3u64),
+                        tick_spacing: 100,
+                    },
+                },
+                x_to_y: true,
+            }],
+            minimum_receive: Some(Uint128::from(100u128)),
+            router: None,
+            source: None,
+            fee_receiver: None,
+            label: None,
+        };
+        let res = execute_collect_fees(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("executor", &[]),
+            vec![requirement.clone()],
+            false,
+            CollectMode::BestEffort,
+        )
+        .unwrap();
+        let collection_id = res.messages[0].id;
+
+        // the v3 swap executed, but no distribute token reached the contract
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: collection_id,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: None,
+                }),
+            },
+        )
+        .unwrap();
+        let event = res
+            .events
+            .iter()
+            .find(|event| event.ty == "collection_proceeds")
+            .unwrap();
+        assert!(event
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "below_minimum_receive" && attr.value == "100"));
+
+        let record = COLLECTION_HISTORY
+            .load(deps.as_ref().storage, collection_id)
+            .unwrap();
+        assert_eq!(record.received, Uint128::zero());
+        assert!(record.error.is_some());
+        let failed = FAILED_COLLECTIONS
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].1.requirement, requirement);
+        assert_eq!(failed[0].1.attempts, 1);
+        assert!(COLLECTION_RUN
+            .may_load(deps.as_ref().storage)
+            .unwrap()
+            .is_none());
+    }
+
+    fn _collect_cw20_fees_with_grants(grants: Vec<Grant>) -> Response {
+        let mut deps = _authz_deps(grants);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("approver", &[]),
+            ExecuteMsg::UpdateCw20PullMode {
+                mode: Cw20PullMode::Authz,
+            },
+        )
+        .unwrap();
+
+        execute_collect_fees(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("executor", &[]),
+            vec![CollectFeeRequirement {
+                approver: Addr::unchecked("approver"),
+                swap_operations: vec![SwapOperation::SwapV3 {
+                    pool_key: PoolKey {
+                        token_x: "cw20_token".into(),
+                        token_y: "distribute_token".into(),
+                        fee_tier: FeeTier {
+                            fee: Percentage(3u64),
+                            tick_spacing: 100,
+                        },
+                    },
+                    x_to_y: true,
+                }],
+                minimum_receive: None,
+                router: None,
+                source: None,
+                fee_receiver: None,
+                label: None,
+            }],
+            false,
+            CollectMode::Atomic,
+        )
+        .unwrap()
+    }
+
+    fn _contract_execution_grant(contract: &str, limit: Any, filter: Any) -> Grant {
+        Grant {
+            authorization: Some(Any {
+                type_url: CONTRACT_EXECUTION_AUTHORIZATION_TYPE_URL.to_string(),
+                value: ContractExecutionAuthorization {
+                    grants: vec![ContractGrant {
+                        contract: contract.to_string(),
+                        limit: Some(limit),
+                        filter: Some(filter),
+                    }],
+                }
+                .encode_to_vec(),
+            }),
+            expiration: None,
+        }
+    }
+
+    #[test]
+    fn test_collect_cw20_fees_through_authz_grant() {
+        let res = _collect_cw20_fees_with_grants(vec![Grant {
+            authorization: Some(Any {
+                type_url: GENERIC_AUTHORIZATION_TYPE_URL.to_string(),
+                value: vec![],
+            }),
+            expiration: None,
+        }]);
+
+        // the approver sends its cw20 to the router itself, no allowance is needed
+        let CosmosMsg::Stargate { type_url, value } = &res.messages[0].msg else {
+            panic!("expected an authz message");
+        };
+        assert_eq!(type_url, MSG_EXEC_TYPE_URL);
+        let exec = MsgExec::decode(value.as_slice()).unwrap();
+        assert_eq!(exec.grantee, mock_env().contract.address.to_string());
+        assert_eq!(exec.msgs[0].type_url, MSG_EXECUTE_CONTRACT_TYPE_URL);
+        let execute_contract = MsgExecuteContract::decode(exec.msgs[0].value.as_slice()).unwrap();
+        assert_eq!(execute_contract.sender, "approver");
+        assert_eq!(execute_contract.contract, "cw20_token");
+        assert!(matches!(
+            from_json::<Cw20ExecuteMsg>(&execute_contract.msg).unwrap(),
+            Cw20ExecuteMsg::Send { contract, amount, .. }
+                if contract == "router" && amount == Uint128::from(1000u128)
+        ));
+    }
+
+    #[test]
+    fn test_collect_cw20_fees_checks_contract_execution_grant() {
+        let max_calls = |remaining: u64| Any {
+            type_url: MAX_CALLS_LIMIT_TYPE_URL.to_string(),
+            value: MaxCallsLimit { remaining }.encode_to_vec(),
+        };
+        let allow_all = Any {
+            type_url: ALLOW_ALL_MESSAGES_FILTER_TYPE_URL.to_string(),
+            value: AllowAllMessagesFilter {}.encode_to_vec(),
+        };
+        let accepted_keys = |keys: &[&str]| Any {
+            type_url: ACCEPTED_MESSAGE_KEYS_FILTER_TYPE_URL.to_string(),
+            value: AcceptedMessageKeysFilter {
+                keys: keys.iter().map(|key| key.to_string()).collect(),
+            }
+            .encode_to_vec(),
+        };
+        let skip_reason = |grant: Grant| {
+            let res = _collect_cw20_fees_with_grants(vec![grant]);
+            from_json::<CollectFeesResult>(&res.data.unwrap())
+                .unwrap()
+                .requirements[0]
+                .skip_reason
+                .clone()
+        };
+
+        assert_eq!(
+            skip_reason(_contract_execution_grant(
+                "cw20_token",
+                max_calls(1),
+                allow_all.clone()
+            )),
+            None
+        );
+        assert_eq!(
+            skip_reason(_contract_execution_grant(
+                "cw20_token",
+                max_calls(1),
+                accepted_keys(&["send", "transfer"])
+            )),
+            None
+        );
+
+        // an exhausted grant, a grant of another contract, or one missing a message is not enough
+        for grant in [
+            _contract_execution_grant("cw20_token", max_calls(0), allow_all.clone()),
+            _contract_execution_grant("other_token", max_calls(1), allow_all.clone()),
+            _contract_execution_grant("cw20_token", max_calls(1), accepted_keys(&["send"])),
+        ] {
+            assert_eq!(skip_reason(grant), Some(CollectFeeSkipReason::NoAuthzGrant));
+        }
+    }
+
     fn _instantiate_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
         let mut deps = mock_dependencies();
         let init_distribution_targets = vec![
@@ -480,6 +3759,11 @@ mod tests {
                 owner: Addr::unchecked("owner"),
                 distribute_token: Addr::unchecked("distribute_token"),
                 router: Some(Addr::unchecked("router")),
+                max_slippage_bps: None,
+                check_authz_grants: true,
+                keeper_bounty: None,
+                permissionless: None,
+                registered_routes_only: false,
             })
         );
 
@@ -504,15 +3788,40 @@ mod tests {
         let _deps = _instantiate_deps();
     }
 
+    #[test]
+    fn test_migrate_keeps_removed_native_reserve() {
+        let mut deps = _instantiate_deps();
+        let migrate_msg = MigrateMsg {
+            new_router: Addr::unchecked("router"),
+        };
+
+        // contracts older than the native reserves get the hard-coded orai reserve back
+        NATIVE_RESERVES.remove(deps.as_mut().storage, DEFAULT_NATIVE_RESERVE_DENOM);
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.2.0").unwrap();
+        migrate(deps.as_mut(), mock_env(), migrate_msg.clone()).unwrap();
+        assert!(NATIVE_RESERVES.has(deps.as_ref().storage, DEFAULT_NATIVE_RESERVE_DENOM));
+        assert_eq!(
+            get_contract_version(deps.as_ref().storage).unwrap().version,
+            CONTRACT_VERSION
+        );
+
+        // a reserve removed by the owner is not written again by later migrations
+        NATIVE_RESERVES.remove(deps.as_mut().storage, DEFAULT_NATIVE_RESERVE_DENOM);
+        migrate(deps.as_mut(), mock_env(), migrate_msg).unwrap();
+        assert!(!NATIVE_RESERVES.has(deps.as_ref().storage, DEFAULT_NATIVE_RESERVE_DENOM));
+    }
+
     #[test]
     fn test_load_target_messages() {
         let mut deps = _instantiate_deps();
 
         let amount_distribute = Uint128::from(1000u128);
-        let distribute_token = Addr::unchecked("distribute_token");
+        let distribute_token = AssetInfo::Token {
+            contract_addr: Addr::unchecked("distribute_token"),
+        };
 
         let messages =
-            _load_target_messages(&mut deps.storage, amount_distribute, distribute_token).unwrap();
+            _load_target_messages(&mut deps.storage, amount_distribute, &distribute_token).unwrap();
 
         assert_eq!(
             messages,
@@ -526,7 +3835,8 @@ mod tests {
                     })
                     .unwrap(),
                     funds: vec![]
-                },
+                }
+                .into(),
                 WasmMsg::Execute {
                     contract_addr: "distribute_token".into(),
                     msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
@@ -536,6 +3846,31 @@ mod tests {
                     .unwrap(),
                     funds: vec![]
                 }
+                .into(),
+            ]
+        );
+
+        // a native distribute token is sent along the hook or through the bank
+        let distribute_token = AssetInfo::NativeToken {
+            denom: "orai".to_string(),
+        };
+        let messages =
+            _load_target_messages(&mut deps.storage, amount_distribute, &distribute_token).unwrap();
+
+        assert_eq!(
+            messages,
+            vec![
+                WasmMsg::Execute {
+                    contract_addr: "target1".into(),
+                    msg: to_json_binary(&"hook1").unwrap(),
+                    funds: coins(400, "orai")
+                }
+                .into(),
+                BankMsg::Send {
+                    to_address: "target2".into(),
+                    amount: coins(600, "orai")
+                }
+                .into(),
             ]
         )
     }
@@ -547,6 +3882,8 @@ mod tests {
         let msg = ExecuteMsg::UpdateConfig {
             owner: Some(Addr::unchecked("new_owner")),
             distribute_token: Some(Addr::unchecked("new_distribute_token")),
+            check_authz_grants: None,
+            registered_routes_only: None,
         };
 
         // act
@@ -563,6 +3900,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_execute_update_max_slippage() {
+        let mut deps = _instantiate_deps();
+        let update_max_slippage =
+            |max_slippage_bps| ExecuteMsg::UpdateMaxSlippage { max_slippage_bps };
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            update_max_slippage(Some(100)),
+        )
+        .unwrap();
+        assert_eq!(
+            CONFIG.load(deps.as_ref().storage).unwrap().max_slippage_bps,
+            Some(100)
+        );
+
+        // the limit can be removed again
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            update_max_slippage(None),
+        )
+        .unwrap();
+        assert_eq!(
+            CONFIG.load(deps.as_ref().storage).unwrap().max_slippage_bps,
+            None
+        );
+    }
+
     #[test]
     fn test_execute_update_distribute_targets() {
         let mut deps = _instantiate_deps();
@@ -625,6 +3994,8 @@ mod tests {
             ExecuteMsg::UpdateConfig {
                 owner: None,
                 distribute_token: None,
+                check_authz_grants: None,
+                registered_routes_only: None,
             },
         )
         .unwrap_err();
@@ -650,4 +4021,53 @@ mod tests {
             ContractError::Unauthorized {}
         );
     }
+
+    #[test]
+    fn test_asset_info_from_string() {
+        let mut deps = mock_dependencies();
+        let native = |denom: &str| AssetInfo::NativeToken {
+            denom: denom.to_string(),
+        };
+
+        // only instantiated contracts are cw20 tokens
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::ContractInfo { contract_addr } if contract_addr == "cw20_token" => {
+                SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&ContractInfoResponse::new(1, "creator")).unwrap(),
+                ))
+            }
+            _ => SystemResult::Err(SystemError::NoSuchContract {
+                addr: "unknown".to_string(),
+            }),
+        });
+
+        // deterministic rules
+        assert_eq!(
+            asset_info_from_string(deps.as_ref(), "ibc/ABCDEF").unwrap(),
+            native("ibc/ABCDEF")
+        );
+        assert_eq!(
+            asset_info_from_string(deps.as_ref(), "factory/creator/token").unwrap(),
+            native("factory/creator/token")
+        );
+        assert_eq!(
+            asset_info_from_string(deps.as_ref(), "cw20_token").unwrap(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("cw20_token")
+            }
+        );
+        assert_eq!(
+            asset_info_from_string(deps.as_ref(), "orai").unwrap(),
+            native("orai")
+        );
+
+        // the registry takes precedence over the rules
+        ASSET_REGISTRY
+            .save(&mut deps.storage, "cw20_token", &native("cw20_token"))
+            .unwrap();
+        assert_eq!(
+            asset_info_from_string(deps.as_ref(), "cw20_token").unwrap(),
+            native("cw20_token")
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -14,4 +14,28 @@ pub enum ContractError {
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
     #[error("Router and approver are not set")]
     RouterAndApproverNotSet {},
+    #[error("Invalid approver route: {reason}")]
+    InvalidApproverRoute { reason: String },
+    #[error("Slippage can not exceed 10000 bps")]
+    InvalidSlippage {},
+    #[error("Keeper bounty can not exceed 10000 bps")]
+    InvalidKeeperBounty {},
+    #[error("Native reserve of {denom} can not exceed 100%")]
+    InvalidNativeReserve { denom: String },
+    #[error("Retention of {asset} can not exceed 100%")]
+    InvalidRetention { asset: String },
+    #[error("Collection {collection_id} received {received}, less than the minimum of {minimum_receive}")]
+    BelowMinimumReceive {
+        collection_id: u64,
+        received: Uint128,
+        minimum_receive: Uint128,
+    },
+    #[error("Approver {approver} is not registered")]
+    ApproverNotRegistered { approver: String },
+    #[error("Executor {executor} exceeded its quota")]
+    ExecutorQuotaExceeded { executor: String },
+    #[error("Executor quota window can not be zero")]
+    InvalidExecutorQuota {},
+    #[error("Permissionless collection requires a threshold with a minimum output")]
+    MissingSlippageProtection {},
 }
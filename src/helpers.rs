@@ -1,23 +1,95 @@
-use cosmwasm_std::Api;
+use cosmos_sdk_proto::cosmos::authz::v1beta1::{Grant, QueryGrantsRequest, QueryGrantsResponse};
+use cosmos_sdk_proto::traits::Message;
+use cosmwasm_std::{
+    to_json_vec, Addr, Binary, ContractResult, Deps, Empty, QuerierWrapper, QueryRequest, StdError,
+    StdResult, SystemResult,
+};
 use oraiswap::asset::AssetInfo;
 
-pub fn asset_info_from_string(api: &dyn Api, asset: String) -> AssetInfo {
-    #[cfg(test)]
-    {
-        let native_tokens = vec!["orai", "atom"];
-        if native_tokens.contains(&asset.as_str()) {
-            return AssetInfo::NativeToken {
-                denom: asset.to_string(),
-            };
-        }
+use crate::state::ASSET_REGISTRY;
+
+pub const AUTHZ_GRANTS_QUERY_PATH: &str = "/cosmos.authz.v1beta1.Query/Grants";
+pub const MSG_EXEC_TYPE_URL: &str = "/cosmos.authz.v1beta1.MsgExec";
+pub const MSG_SEND_TYPE_URL: &str = "/cosmos.bank.v1beta1.MsgSend";
+pub const MSG_EXECUTE_CONTRACT_TYPE_URL: &str = "/cosmwasm.wasm.v1.MsgExecuteContract";
+pub const MSG_WITHDRAW_DELEGATOR_REWARD_TYPE_URL: &str =
+    "/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward";
+pub const MSG_WITHDRAW_VALIDATOR_COMMISSION_TYPE_URL: &str =
+    "/cosmos.distribution.v1beta1.MsgWithdrawValidatorCommission";
+pub const SEND_AUTHORIZATION_TYPE_URL: &str = "/cosmos.bank.v1beta1.SendAuthorization";
+pub const GENERIC_AUTHORIZATION_TYPE_URL: &str = "/cosmos.authz.v1beta1.GenericAuthorization";
+pub const CONTRACT_EXECUTION_AUTHORIZATION_TYPE_URL: &str =
+    "/cosmwasm.wasm.v1.ContractExecutionAuthorization";
+pub const MAX_CALLS_LIMIT_TYPE_URL: &str = "/cosmwasm.wasm.v1.MaxCallsLimit";
+pub const MAX_FUNDS_LIMIT_TYPE_URL: &str = "/cosmwasm.wasm.v1.MaxFundsLimit";
+pub const COMBINED_LIMIT_TYPE_URL: &str = "/cosmwasm.wasm.v1.CombinedLimit";
+pub const ALLOW_ALL_MESSAGES_FILTER_TYPE_URL: &str = "/cosmwasm.wasm.v1.AllowAllMessagesFilter";
+pub const ACCEPTED_MESSAGE_KEYS_FILTER_TYPE_URL: &str =
+    "/cosmwasm.wasm.v1.AcceptedMessageKeysFilter";
+
+/// Resolves a pool token or contract address into an asset, the same way in tests and on chain:
+/// 1. an entry of the owner maintained asset registry
+/// 2. `ibc/` and `factory/` denoms are native tokens
+/// 3. the address of an instantiated contract is a cw20 token
+/// 4. anything else is a native denom
+///
+/// Whether a string is a valid address depends on the api, a denom like `orai` passes the
+/// mock one, so only the existence of the contract is trusted.
+pub fn asset_info_from_string(deps: Deps, asset: &str) -> StdResult<AssetInfo> {
+    if let Some(asset_info) = ASSET_REGISTRY.may_load(deps.storage, asset)? {
+        return Ok(asset_info);
+    }
+
+    if asset.starts_with("ibc/") || asset.starts_with("factory/") {
+        return Ok(AssetInfo::NativeToken {
+            denom: asset.to_string(),
+        });
     }
 
-    match api.addr_validate(&asset) {
-        Ok(token_addr) => AssetInfo::Token {
-            contract_addr: token_addr,
+    Ok(match deps.querier.query_wasm_contract_info(asset) {
+        Ok(_) => AssetInfo::Token {
+            contract_addr: Addr::unchecked(asset),
         },
         Err(_) => AssetInfo::NativeToken {
             denom: asset.to_string(),
         },
+    })
+}
+
+/// Queries the authz grants from `granter` to `grantee` for `msg_type_url` through a stargate query.
+/// The response is protobuf encoded, so the raw query is used instead of `query`.
+pub fn query_authz_grants(
+    querier: &QuerierWrapper,
+    granter: &Addr,
+    grantee: &Addr,
+    msg_type_url: &str,
+) -> StdResult<Vec<Grant>> {
+    let request: QueryRequest<Empty> = QueryRequest::Stargate {
+        path: AUTHZ_GRANTS_QUERY_PATH.to_string(),
+        data: Binary::from(
+            QueryGrantsRequest {
+                granter: granter.to_string(),
+                grantee: grantee.to_string(),
+                msg_type_url: msg_type_url.to_string(),
+                pagination: None,
+            }
+            .encode_to_vec(),
+        ),
+    };
+
+    match querier.raw_query(&to_json_vec(&request)?) {
+        SystemResult::Err(system_err) => Err(StdError::generic_err(format!(
+            "Querier system error: {}",
+            system_err
+        ))),
+        SystemResult::Ok(ContractResult::Err(contract_err)) => Err(StdError::generic_err(format!(
+            "Querier contract error: {}",
+            contract_err
+        ))),
+        SystemResult::Ok(ContractResult::Ok(value)) => {
+            QueryGrantsResponse::decode(value.as_slice())
+                .map(|response| response.grants)
+                .map_err(|err| StdError::parse_err("QueryGrantsResponse", err))
+        }
     }
 }
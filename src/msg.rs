@@ -1,8 +1,16 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Uint128};
-use oraiswap::mixed_router::SwapOperation;
+use std::fmt;
 
-use crate::state::{Config, DistributeTarget};
+use cosmwasm_std::{Addr, CosmosMsg, StdError, Timestamp, Uint128};
+use oraiswap::asset::AssetInfo;
+
+use crate::state::{
+    ApproverLimits, ApproverRoute, ApproverSettings, AssetRetention, ChunkLimit, CollectThreshold,
+    CollectionRecord, Config, Cw20PullMode, DistributeTarget, ExecutorQuota, ExecutorStats,
+    FailedCollection, KeeperBounty, NativeReserve, PermissionlessMode, RegisteredApprover,
+    RouterInfo,
+};
+pub use crate::state::{CollectFeeRequirement, CollectMode, CollectSource};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -21,6 +29,8 @@ pub enum ExecuteMsg {
     UpdateConfig {
         owner: Option<Addr>,
         distribute_token: Option<Addr>,
+        check_authz_grants: Option<bool>,
+        registered_routes_only: Option<bool>,
     },
     UpdateDistributeTarget {
         distribute_targets: Vec<DistributeTarget>,
@@ -29,22 +39,271 @@ pub enum ExecuteMsg {
         executors: Vec<Addr>,
         permission: bool,
     },
+    /// Unsetting `quota` lifts the rate limits of the executor
+    UpdateExecutorQuota {
+        executor: Addr,
+        quota: Option<ExecutorQuota>,
+    },
     Distribute {
         amount_distribute: Uint128,
     },
+    /// Unsetting `max_slippage_bps` sends the executor supplied minimum receive as is
+    UpdateMaxSlippage {
+        max_slippage_bps: Option<u16>,
+    },
+    /// Unsetting `keeper_bounty` stops paying executors
+    UpdateKeeperBounty {
+        keeper_bounty: Option<KeeperBounty>,
+    },
+    /// Unsetting `permissionless` restricts collection to executors again.
+    /// Enabling it requires a threshold with a minimum output. Keepers can not be trusted
+    /// with the minimum receive nor with the quote behind the max slippage, so offer assets
+    /// without a `min_output` are skipped in permissionless collections
+    UpdatePermissionlessMode {
+        permissionless: Option<PermissionlessMode>,
+    },
+    /// Registers or replaces the router stored under `router.name`
+    UpdateRouter {
+        router: RouterInfo,
+    },
+    RemoveRouter {
+        name: String,
+    },
+    UpdateFeeReceivers {
+        receivers: Vec<FeeReceiver>,
+    },
+    /// Collections are only labelled with listed labels, others are skipped
+    UpdateRevenueLabels {
+        labels: Vec<String>,
+        listed: bool,
+    },
+    /// Entries without `asset_info` are removed from the registry
+    UpdateAssetRegistry {
+        entries: Vec<AssetRegistryEntry>,
+    },
+    /// Once the allowlist is not empty, only listed offer assets are collected
+    UpdateAllowedOfferAssets {
+        assets: Vec<AssetInfo>,
+        allowed: bool,
+    },
+    UpdateDeniedOfferAssets {
+        assets: Vec<AssetInfo>,
+        denied: bool,
+    },
+    /// A threshold with zero `min_amount` and no `min_output` removes the asset
+    UpdateCollectThresholds {
+        thresholds: Vec<CollectThreshold>,
+    },
+    /// A zero `max_per_block` removes the limit of the asset
+    UpdateChunkLimits {
+        limits: Vec<ChunkLimit>,
+    },
+    /// A reserve with zero amount and no percent removes the denom
+    UpdateNativeReserves {
+        reserves: Vec<NativeReserve>,
+    },
+    RegisterApprover {
+        approver: Addr,
+        routes: Vec<ApproverRoute>,
+    },
+    RemoveApprover {
+        approver: Addr,
+    },
+    /// Drops failed collections of `approver` from the retry queue, all of them when `ids` is not set
+    RemoveFailedCollections {
+        approver: Addr,
+        ids: Option<Vec<u64>>,
+    },
+    ////////////////////
+    /// Approver API ///
+    ////////////////////
+    /// Allows the sender's fees to be collected within the given limits
+    OptInCollection {
+        limits: ApproverLimits,
+    },
+    /// Stops any further collection from the sender
+    OptOutCollection {},
+    /// Sets how the cw20 fees of the sender are pulled, through its allowances by default
+    UpdateCw20PullMode {
+        mode: Cw20PullMode,
+    },
+    /// Sets the balances kept in the account of `approver`, callable by the approver and the owner.
+    /// Both update the same entries, so the last update of an asset wins.
+    /// A retention with zero amount and no percent removes the asset
+    UpdateRetentions {
+        approver: Addr,
+        retentions: Vec<AssetRetention>,
+    },
     /////////////////
     ///Executors////
     ///////////////
+    /// With `auto_distribute`, the distribute token received by the swaps
+    /// is sent to the distribution targets once all of them settled.
+    /// In permissionless mode anyone may call it, but only the registered routes
+    /// of the requested approvers are collected
     CollectFees {
         collect_fee_requirements: Vec<CollectFeeRequirement>,
+        auto_distribute: Option<bool>,
+        mode: Option<CollectMode>,
+    },
+    /// Collects from registered approvers using their stored routes,
+    /// all registered approvers are collected when `approvers` is not set
+    CollectRegisteredFees {
+        approvers: Option<Vec<Addr>>,
+        auto_distribute: Option<bool>,
+        mode: Option<CollectMode>,
+    },
+    /// Collects again the requirements that failed in best-effort mode, in key order starting
+    /// after the one retried last and wrapping around
+    RetryFailedCollections {
+        limit: Option<u32>,
+    },
+    ////////////////////
+    /// Internal API ///
+    ////////////////////
+    /// Runs the pull and swap messages of a best-effort requirement as a single sub message,
+    /// so a failing swap also reverts its pulls. Only callable by the contract itself
+    RunCollection {
+        messages: Vec<CosmosMsg>,
     },
 }
 
+/// Allows `addr` to receive proceeds under `name`, removed when `addr` is not set
 #[cw_serde]
-pub struct CollectFeeRequirement {
+pub struct FeeReceiver {
+    pub name: String,
+    pub addr: Option<Addr>,
+}
+
+/// Resolves the pool token `asset` to `asset_info`, ahead of the built-in rules
+#[cw_serde]
+pub struct AssetRegistryEntry {
+    pub asset: String,
+    pub asset_info: Option<AssetInfo>,
+}
+
+#[cw_serde]
+pub enum CollectFeeStatus {
+    Collected,
+    /// the requirement is collected in reply once its fees are claimed
+    Claimed,
+    Skipped,
+    /// the offer asset is refused by the allow and deny lists of the owner
+    Rejected,
+}
+
+#[cw_serde]
+pub enum CollectFeeSkipReason {
+    EmptySwapOperations,
+    UnsupportedSwapOperation,
+    InvalidFinalAskAsset,
+    ZeroBalance,
+    BelowGasReserve,
+    BelowMinBalance,
+    CollectionCapReached,
+    ApproverOptedOut,
+    ApproverCooldown,
+    AskAssetNotAllowed,
+    InsufficientAllowance,
+    NoAuthzGrant,
+    UnknownRouter,
+    RouterPaused,
+    NothingToClaim,
+    ChunkLimitReached,
+    BelowThreshold,
+    BelowMinOutput,
+    OfferAssetDenied,
+    OfferAssetNotAllowed,
+    GrantQueryFailed { msg: String },
+    SimulationFailed { msg: String },
+    EncodingError { msg: String },
+    UnresolvedAsset { msg: String },
+    UnknownFeeReceiver,
+    NoSlippageProtection,
+    UnknownLabel,
+    UnregisteredRoute,
+}
+
+impl fmt::Display for CollectFeeSkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CollectFeeSkipReason::EmptySwapOperations => write!(f, "empty_swap_operations"),
+            CollectFeeSkipReason::UnsupportedSwapOperation => {
+                write!(f, "unsupported_swap_operation")
+            }
+            CollectFeeSkipReason::InvalidFinalAskAsset => write!(f, "invalid_final_ask_asset"),
+            CollectFeeSkipReason::ZeroBalance => write!(f, "zero_balance"),
+            CollectFeeSkipReason::BelowGasReserve => write!(f, "below_gas_reserve"),
+            CollectFeeSkipReason::BelowMinBalance => write!(f, "below_min_balance"),
+            CollectFeeSkipReason::CollectionCapReached => write!(f, "collection_cap_reached"),
+            CollectFeeSkipReason::ApproverOptedOut => write!(f, "approver_opted_out"),
+            CollectFeeSkipReason::ApproverCooldown => write!(f, "approver_cooldown"),
+            CollectFeeSkipReason::AskAssetNotAllowed => write!(f, "ask_asset_not_allowed"),
+            CollectFeeSkipReason::InsufficientAllowance => write!(f, "insufficient_allowance"),
+            CollectFeeSkipReason::NoAuthzGrant => write!(f, "no_authz_grant"),
+            CollectFeeSkipReason::UnknownRouter => write!(f, "unknown_router"),
+            CollectFeeSkipReason::RouterPaused => write!(f, "router_paused"),
+            CollectFeeSkipReason::NothingToClaim => write!(f, "nothing_to_claim"),
+            CollectFeeSkipReason::ChunkLimitReached => write!(f, "chunk_limit_reached"),
+            CollectFeeSkipReason::BelowThreshold => write!(f, "below_threshold"),
+            CollectFeeSkipReason::BelowMinOutput => write!(f, "below_min_output"),
+            CollectFeeSkipReason::OfferAssetDenied => write!(f, "offer_asset_denied"),
+            CollectFeeSkipReason::OfferAssetNotAllowed => write!(f, "offer_asset_not_allowed"),
+            CollectFeeSkipReason::GrantQueryFailed { msg } => {
+                write!(f, "grant_query_failed: {}", msg)
+            }
+            CollectFeeSkipReason::SimulationFailed { msg } => {
+                write!(f, "simulation_failed: {}", msg)
+            }
+            CollectFeeSkipReason::EncodingError { msg } => write!(f, "encoding_error: {}", msg),
+            CollectFeeSkipReason::UnknownFeeReceiver => write!(f, "unknown_fee_receiver"),
+            CollectFeeSkipReason::NoSlippageProtection => write!(f, "no_slippage_protection"),
+            CollectFeeSkipReason::UnknownLabel => write!(f, "unknown_label"),
+            CollectFeeSkipReason::UnregisteredRoute => write!(f, "unregistered_route"),
+            CollectFeeSkipReason::UnresolvedAsset { msg } => write!(f, "unresolved_asset: {}", msg),
+        }
+    }
+}
+
+impl CollectFeeSkipReason {
+    /// Whether the requirement was refused by the offer asset policy
+    pub fn is_policy_rejection(&self) -> bool {
+        matches!(
+            self,
+            CollectFeeSkipReason::OfferAssetDenied
+                | CollectFeeSkipReason::OfferAssetNotAllowed
+                | CollectFeeSkipReason::UnregisteredRoute
+        )
+    }
+}
+
+impl From<StdError> for CollectFeeSkipReason {
+    fn from(err: StdError) -> Self {
+        CollectFeeSkipReason::EncodingError {
+            msg: err.to_string(),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct CollectFeeRequirementResult {
     pub approver: Addr,
-    pub swap_operations: Vec<SwapOperation>,
+    pub offer_asset: Option<AssetInfo>,
+    pub amount: Uint128,
+    /// minimum receive sent to the router, after slippage protection
     pub minimum_receive: Option<Uint128>,
+    /// id of the `CollectionHistory` record holding the received amount
+    pub collection_id: Option<u64>,
+    /// router the swap was sent to
+    pub router: Option<Addr>,
+    pub status: CollectFeeStatus,
+    pub skip_reason: Option<CollectFeeSkipReason>,
+}
+
+/// Returned as the response data of `CollectFees`, one entry per requirement in request order.
+#[cw_serde]
+pub struct CollectFeesResult {
+    pub requirements: Vec<CollectFeeRequirementResult>,
 }
 
 #[cw_serde]
@@ -56,6 +315,66 @@ pub enum QueryMsg {
     DistributeTargets {},
     #[returns(bool)]
     IsExecutor { addr: Addr },
+    /// Rate limits of `executor` and what is left of them in the current window
+    #[returns(ExecutorQuotaResponse)]
+    ExecutorQuota { executor: Addr },
+    #[returns(ExecutorStatsResponse)]
+    ExecutorStats { addr: Addr },
+    #[returns(AllExecutorStatsResponse)]
+    AllExecutorStats {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    #[returns(CollectionHistoryResponse)]
+    CollectionHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(NativeReservesResponse)]
+    NativeReserves {},
+    #[returns(RetentionsResponse)]
+    Retentions { approver: Addr },
+    #[returns(RoutersResponse)]
+    Routers {},
+    #[returns(AssetRegistryResponse)]
+    AssetRegistry {},
+    #[returns(FeeReceiversResponse)]
+    FeeReceivers {},
+    /// Requirements whose last best-effort collection failed
+    #[returns(FailedCollectionsResponse)]
+    FailedCollections {
+        start_after: Option<(Addr, u64)>,
+        limit: Option<u32>,
+    },
+    /// Proceeds of the labelled collections between the epochs of `from` and `to`, both included,
+    /// spanning at most 366 daily epochs
+    #[returns(RevenueBySourceResponse)]
+    RevenueBySource { from: Timestamp, to: Timestamp },
+    #[returns(RevenueLabelsResponse)]
+    RevenueLabels {},
+    #[returns(OfferAssetsResponse)]
+    AllowedOfferAssets {},
+    #[returns(OfferAssetsResponse)]
+    DeniedOfferAssets {},
+    #[returns(CollectThresholdsResponse)]
+    CollectThresholds {},
+    #[returns(ChunkLimitsResponse)]
+    ChunkLimits {},
+    /// Amount of `asset_info` left to collect from `approver` by the chunk limit
+    #[returns(QueuedRemainderResponse)]
+    QueuedRemainder {
+        approver: Addr,
+        asset_info: AssetInfo,
+    },
+    #[returns(ApproverSettingsResponse)]
+    ApproverSettings { addr: Addr },
+    #[returns(RegisteredApproverResponse)]
+    RegisteredApprover { addr: Addr },
+    #[returns(RegisteredApproversResponse)]
+    RegisteredApprovers {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
@@ -64,6 +383,78 @@ pub struct ConfigResponse(pub Config);
 #[cw_serde]
 pub struct DistributeTargetsResponse(pub Vec<DistributeTarget>);
 
+#[cw_serde]
+pub struct CollectionHistoryResponse(pub Vec<CollectionRecord>);
+
+#[cw_serde]
+pub struct ExecutorQuotaResponse {
+    pub quota: Option<ExecutorQuota>,
+    pub remaining_calls: Option<u32>,
+    pub remaining_value: Option<Uint128>,
+    /// end of the current window, once the executor collected in it
+    pub window_end: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct ExecutorStatsResponse(pub Option<ExecutorStats>);
+
+#[cw_serde]
+pub struct AllExecutorStatsResponse(pub Vec<ExecutorStats>);
+
+#[cw_serde]
+pub struct NativeReservesResponse(pub Vec<NativeReserve>);
+
+#[cw_serde]
+pub struct RetentionsResponse(pub Vec<AssetRetention>);
+
+#[cw_serde]
+pub struct SourceRevenue {
+    pub label: String,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct RevenueBySourceResponse(pub Vec<SourceRevenue>);
+
+#[cw_serde]
+pub struct RevenueLabelsResponse(pub Vec<String>);
+
+#[cw_serde]
+pub struct RoutersResponse(pub Vec<RouterInfo>);
+
+#[cw_serde]
+pub struct FailedCollectionsResponse(pub Vec<FailedCollection>);
+
+#[cw_serde]
+pub struct FeeReceiversResponse(pub Vec<FeeReceiver>);
+
+#[cw_serde]
+pub struct AssetRegistryResponse(pub Vec<AssetRegistryEntry>);
+
+#[cw_serde]
+pub struct OfferAssetsResponse(pub Vec<AssetInfo>);
+
+#[cw_serde]
+pub struct CollectThresholdsResponse(pub Vec<CollectThreshold>);
+
+#[cw_serde]
+pub struct ChunkLimitsResponse(pub Vec<ChunkLimit>);
+
+#[cw_serde]
+pub struct QueuedRemainderResponse(pub Uint128);
+
+#[cw_serde]
+pub struct ApproverSettingsResponse {
+    pub settings: Option<ApproverSettings>,
+    pub last_collected: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct RegisteredApproverResponse(pub Option<RegisteredApprover>);
+
+#[cw_serde]
+pub struct RegisteredApproversResponse(pub Vec<RegisteredApprover>);
+
 #[cw_serde]
 pub struct MigrateMsg {
     pub new_router: Addr,
@@ -4,7 +4,7 @@ use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Addr, Uint128};
 use cw_multi_test::{AppResponse, ContractWrapper, Executor};
 
-use crate::contract::{execute, instantiate, migrate, query};
+use crate::contract::{execute, instantiate, migrate, query, reply};
 
 use super::tests::StargateAccpetingModuleApp;
 
@@ -17,7 +17,9 @@ impl TreasuryContract {
     }
 
     pub fn store_code(app: &mut StargateAccpetingModuleApp) -> u64 {
-        let contract = ContractWrapper::new(execute, instantiate, query).with_migrate(migrate);
+        let contract = ContractWrapper::new(execute, instantiate, query)
+            .with_migrate(migrate)
+            .with_reply(reply);
         app.store_code(Box::new(contract))
     }
 
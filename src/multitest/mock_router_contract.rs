@@ -1,17 +1,15 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, DepsMut, Env, MessageInfo, Response, StdResult, WasmMsg,
+    to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 use cw_multi_test::{ContractWrapper, Executor};
 use cw_storage_plus::Item;
-use oraiswap::mixed_router::ExecuteMsg as RouterExecuteMsg;
+use oraiswap::mixed_router::{ExecuteMsg as RouterExecuteMsg, QueryMsg as RouterQueryMsg};
+use oraiswap::router::SimulateSwapOperationsResponse;
 
 use super::tests::StargateAccpetingModuleApp;
 
-#[cw_serde]
-pub enum MockQueryMsg {}
-
 #[cw_serde]
 pub enum Cw20Hook {
     Ping {},
@@ -99,7 +97,17 @@ impl MockRouter {
                 }
             },
             instantiate,
-            |_, _, _: MockQueryMsg| -> StdResult<Binary> { Ok(Binary::default()) },
+            |_: Deps, _: Env, msg: RouterQueryMsg| -> StdResult<Binary> {
+                // simulate 1:1 like the swaps
+                match msg {
+                    RouterQueryMsg::SimulateSwapOperations { offer_amount, .. } => {
+                        to_json_binary(&SimulateSwapOperationsResponse {
+                            amount: offer_amount,
+                        })
+                    }
+                    _ => Ok(Binary::default()),
+                }
+            },
         );
         app.store_code(Box::new(contract))
     }
@@ -1,11 +1,22 @@
 use crate::contract::{execute, execute_collect_fees, query};
-use crate::msg::{CollectFeeRequirement, ExecuteMsg, QueryMsg};
-use crate::state::{Config, CONFIG, EXECUTORS};
+use crate::msg::{
+    AllExecutorStatsResponse, AssetRegistryEntry, AssetRegistryResponse, CollectFeeRequirement,
+    CollectFeeSkipReason, CollectFeeStatus, CollectFeesResult, CollectMode, CollectSource,
+    CollectionHistoryResponse, ExecuteMsg, ExecutorQuotaResponse, ExecutorStatsResponse,
+    FailedCollectionsResponse, FeeReceiver, FeeReceiversResponse, NativeReservesResponse,
+    OfferAssetsResponse, QueryMsg, QueuedRemainderResponse, RegisteredApproverResponse,
+    RetentionsResponse, RevenueBySourceResponse, RoutersResponse, SourceRevenue,
+};
+use crate::state::{
+    ApproverLimits, ApproverRoute, AssetRetention, ChunkLimit, CollectThreshold, Config,
+    ExecutorQuota, ExecutorStats, KeeperBounty, NativeReserve, PermissionlessMode, RouterInfo,
+    RouterKind, CONFIG, EXECUTORS,
+};
 use crate::{state::DistributeTarget, ContractError};
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi};
 use cosmwasm_std::{
-    coin, from_json, to_json_binary, Addr, Empty, Event, GovMsg, IbcMsg, IbcQuery, MemoryStorage,
-    Uint128,
+    coin, from_json, to_json_binary, Addr, Decimal, Empty, Event, GovMsg, IbcMsg, IbcQuery,
+    MemoryStorage, Uint128,
 };
 use cw20::{BalanceResponse, Cw20ExecuteMsg};
 use cw_multi_test::{
@@ -114,6 +125,21 @@ fn mock_app() -> (
     )
     .unwrap();
 
+    // the accepting stargate module answers the authz grants query with empty data,
+    // which reads as no grant at all
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateConfig {
+            owner: None,
+            distribute_token: None,
+            check_authz_grants: Some(false),
+            registered_routes_only: None,
+        },
+        &[],
+    )
+    .unwrap();
+
     // send token to router and owner
     usdc.transfer(
         &mut app,
@@ -221,6 +247,11 @@ fn test_execute_collect_fees_router_approver_not_set() {
                 owner: Addr::unchecked("owner"),
                 distribute_token: Addr::unchecked("token"),
                 router: None,
+                max_slippage_bps: None,
+                check_authz_grants: false,
+                keeper_bounty: None,
+                permissionless: None,
+                registered_routes_only: false,
             },
         )
         .unwrap();
@@ -247,7 +278,13 @@ fn test_execute_collect_fees_router_approver_not_set() {
             approver: Addr::unchecked("owner"),
             swap_operations: vec![],
             minimum_receive: None,
+            router: None,
+            source: None,
+            fee_receiver: None,
+            label: None,
         }],
+        false,
+        CollectMode::Atomic,
     )
     .unwrap_err();
     assert_eq!(result, ContractError::RouterAndApproverNotSet {});
@@ -263,6 +300,11 @@ fn test_execute_collect_fees_unauthorize() {
                 owner: Addr::unchecked("owner"),
                 distribute_token: Addr::unchecked("token"),
                 router: None,
+                max_slippage_bps: None,
+                check_authz_grants: false,
+                keeper_bounty: None,
+                permissionless: None,
+                registered_routes_only: false,
             },
         )
         .unwrap();
@@ -287,7 +329,13 @@ fn test_execute_collect_fees_unauthorize() {
             approver: Addr::unchecked("owner"),
             swap_operations: vec![],
             minimum_receive: None,
+            router: None,
+            source: None,
+            fee_receiver: None,
+            label: None,
         }],
+        false,
+        CollectMode::Atomic,
     )
     .unwrap_err();
 
@@ -346,6 +394,10 @@ fn test_collect_fees_balance_distribute() {
                             x_to_y: true,
                         }],
                         minimum_receive: None,
+                        router: None,
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
                     },
                     CollectFeeRequirement {
                         approver: Addr::unchecked("owner"),
@@ -361,6 +413,10 @@ fn test_collect_fees_balance_distribute() {
                             x_to_y: true,
                         }],
                         minimum_receive: None,
+                        router: None,
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
                     },
                     CollectFeeRequirement {
                         approver: Addr::unchecked("owner"),
@@ -376,8 +432,14 @@ fn test_collect_fees_balance_distribute() {
                             x_to_y: true,
                         }],
                         minimum_receive: None,
+                        router: None,
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
                     },
                 ],
+                auto_distribute: None,
+                mode: None,
             },
             &[],
         )
@@ -401,3 +463,2949 @@ fn test_collect_fees_balance_distribute() {
             .unwrap()
     );
 }
+
+#[test]
+fn test_collect_fees_reports_skipped_requirements() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    //act
+    let response = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![
+                    CollectFeeRequirement {
+                        approver: owner.clone(),
+                        swap_operations: vec![],
+                        minimum_receive: None,
+                        router: None,
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                    CollectFeeRequirement {
+                        approver: owner.clone(),
+                        swap_operations: vec![SwapOperation::SwapV3 {
+                            pool_key: PoolKey {
+                                token_x: "orai".into(),
+                                token_y: cw20.addr().to_string(),
+                                fee_tier: FeeTier {
+                                    fee: Percentage(3u64),
+                                    tick_spacing: 100,
+                                },
+                            },
+                            x_to_y: true,
+                        }],
+                        minimum_receive: None,
+                        router: None,
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                    CollectFeeRequirement {
+                        approver: Addr::unchecked("finance"),
+                        swap_operations: vec![SwapOperation::SwapV3 {
+                            pool_key: PoolKey {
+                                token_x: cw20.addr().to_string(),
+                                token_y: usdc.addr().to_string(),
+                                fee_tier: FeeTier {
+                                    fee: Percentage(3u64),
+                                    tick_spacing: 100,
+                                },
+                            },
+                            x_to_y: true,
+                        }],
+                        minimum_receive: None,
+                        router: None,
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                ],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // assert
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(result.requirements.len(), 3);
+    assert!(result
+        .requirements
+        .iter()
+        .all(|requirement| requirement.status == CollectFeeStatus::Skipped));
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::EmptySwapOperations)
+    );
+    assert_eq!(
+        result.requirements[1].skip_reason,
+        Some(CollectFeeSkipReason::InvalidFinalAskAsset)
+    );
+    assert_eq!(
+        result.requirements[1].offer_asset,
+        Some(AssetInfo::NativeToken {
+            denom: "orai".to_string()
+        })
+    );
+    assert_eq!(
+        result.requirements[2].skip_reason,
+        Some(CollectFeeSkipReason::ZeroBalance)
+    );
+
+    let skip_reasons = response
+        .events
+        .iter()
+        .filter(|event| event.ty == "wasm-collect_fee")
+        .flat_map(|event| {
+            event
+                .attributes
+                .iter()
+                .filter(|attr| attr.key == "skip_reason")
+                .map(|attr| attr.value.clone())
+        })
+        .collect::<Vec<String>>();
+    assert_eq!(
+        skip_reasons,
+        vec![
+            "empty_swap_operations",
+            "invalid_final_ask_asset",
+            "zero_balance"
+        ]
+    );
+}
+
+#[test]
+fn test_collect_registered_fees() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(INITIAL_BALANCE),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let cw20_route = ApproverRoute {
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x: cw20.addr().to_string(),
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        fee_receiver: None,
+        label: None,
+        router: None,
+    };
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &ExecuteMsg::RegisterApprover {
+                approver: owner.clone(),
+                routes: vec![cw20_route.clone()],
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::RegisterApprover {
+                approver: owner.clone(),
+                routes: vec![ApproverRoute {
+                    swap_operations: vec![SwapOperation::SwapV3 {
+                        pool_key: PoolKey {
+                            token_x: cw20.addr().to_string(),
+                            token_y: "orai".into(),
+                            fee_tier: FeeTier {
+                                fee: Percentage(3u64),
+                                tick_spacing: 100,
+                            },
+                        },
+                        x_to_y: true,
+                    }],
+                    fee_receiver: None,
+                    label: None,
+                    router: None,
+                }],
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidApproverRoute {
+            reason: "invalid_final_ask_asset".to_string()
+        }
+    );
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::RegisterApprover {
+                approver: owner.clone(),
+                routes: vec![ApproverRoute {
+                    router: Some("missing".to_string()),
+                    ..cw20_route.clone()
+                }],
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidApproverRoute {
+            reason: "unknown_router".to_string()
+        }
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::RegisterApprover {
+            approver: owner.clone(),
+            routes: vec![cw20_route.clone()],
+        },
+        &[],
+    )
+    .unwrap();
+
+    let registered: RegisteredApproverResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::RegisteredApprover {
+                addr: owner.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(registered.0.unwrap().routes, vec![cw20_route]);
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectRegisteredFees {
+                approvers: Some(vec![Addr::unchecked("finance")]),
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::ApproverNotRegistered {
+            approver: "finance".to_string()
+        }
+    );
+
+    // act
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::CollectRegisteredFees {
+            approvers: None,
+            auto_distribute: None,
+            mode: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // assert
+    let balance = cw20.query_balance(&app, router.addr());
+    let usdc_treasury_balance = usdc.query_balance(&app, treasury.addr());
+    assert_eq!(balance.balance, Uint128::from(INITIAL_BALANCE));
+    assert_eq!(
+        usdc_treasury_balance.balance,
+        Uint128::from(INITIAL_BALANCE)
+    );
+}
+
+#[test]
+fn test_collect_fees_registered_routes_only() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(INITIAL_BALANCE),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let swap_operations = vec![SwapOperation::SwapV3 {
+        pool_key: PoolKey {
+            token_x: cw20.addr().to_string(),
+            token_y: usdc.addr().to_string(),
+            fee_tier: FeeTier {
+                fee: Percentage(3u64),
+                tick_spacing: 100,
+            },
+        },
+        x_to_y: true,
+    }];
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::RegisterApprover {
+            approver: owner.clone(),
+            routes: vec![ApproverRoute {
+                swap_operations: swap_operations.clone(),
+                fee_receiver: None,
+                label: None,
+                router: None,
+            }],
+        },
+        &[],
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateConfig {
+                owner: None,
+                distribute_token: None,
+                check_authz_grants: None,
+                registered_routes_only: Some(true),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateConfig {
+            owner: None,
+            distribute_token: None,
+            check_authz_grants: None,
+            registered_routes_only: Some(true),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // act: the executor asks for an approver and a route nobody registered
+    let response = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![
+                    CollectFeeRequirement {
+                        approver: owner.clone(),
+                        swap_operations: swap_operations.clone(),
+                        minimum_receive: None,
+                        router: Some("default".to_string()),
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                    CollectFeeRequirement {
+                        approver: Addr::unchecked("finance"),
+                        swap_operations,
+                        minimum_receive: None,
+                        router: None,
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                ],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // assert
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    for requirement in result.requirements {
+        assert_eq!(requirement.status, CollectFeeStatus::Rejected);
+        assert_eq!(
+            requirement.skip_reason,
+            Some(CollectFeeSkipReason::UnregisteredRoute)
+        );
+    }
+
+    // the registered route itself is still collected
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Collected);
+}
+
+fn collect_cw20_fees(
+    app: &mut StargateAccpetingModuleApp,
+    treasury: &TreasuryContract,
+    cw20: &MockCw20Contract,
+    usdc: &MockCw20Contract,
+) -> CollectFeesResult {
+    collect_cw20_fees_batch(app, treasury, cw20, usdc, 1)
+}
+
+/// Collects the same cw20 requirement `count` times in a single call.
+fn collect_cw20_fees_batch(
+    app: &mut StargateAccpetingModuleApp,
+    treasury: &TreasuryContract,
+    cw20: &MockCw20Contract,
+    usdc: &MockCw20Contract,
+    count: usize,
+) -> CollectFeesResult {
+    let requirement = CollectFeeRequirement {
+        approver: Addr::unchecked("owner"),
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x: cw20.addr().to_string(),
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        minimum_receive: None,
+        router: None,
+        source: None,
+        fee_receiver: None,
+        label: None,
+    };
+    let response = app
+        .execute_contract(
+            Addr::unchecked("owner"),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![requirement; count],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap()
+}
+
+#[test]
+fn test_collect_fees_respects_approver_limits() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(INITIAL_BALANCE),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::OptInCollection {
+            limits: ApproverLimits {
+                max_per_collection: Some(Uint128::from(1000u128)),
+                min_balance: None,
+                allowed_ask_assets: None,
+                cooldown: Some(60),
+            },
+        },
+        &[],
+    )
+    .unwrap();
+
+    // act & assert
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(result.requirements[0].amount, Uint128::from(1000u128));
+    assert_eq!(
+        cw20.query_balance(&app, router.addr()).balance,
+        Uint128::from(1000u128)
+    );
+
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::ApproverCooldown)
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(61));
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Collected);
+    assert_eq!(
+        cw20.query_balance(&app, router.addr()).balance,
+        Uint128::from(2000u128)
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::OptOutCollection {},
+        &[],
+    )
+    .unwrap();
+
+    app.update_block(|block| block.time = block.time.plus_seconds(61));
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::ApproverOptedOut)
+    );
+}
+
+#[test]
+fn test_collect_fees_applies_approver_limits_across_batch() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(INITIAL_BALANCE),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::OptInCollection {
+            limits: ApproverLimits {
+                max_per_collection: Some(Uint128::from(1000u128)),
+                min_balance: None,
+                allowed_ask_assets: None,
+                cooldown: None,
+            },
+        },
+        &[],
+    )
+    .unwrap();
+
+    // act & assert: the cap holds for the whole call, not for every requirement
+    let result = collect_cw20_fees_batch(&mut app, &treasury, &cw20, &usdc, 2);
+    assert_eq!(result.requirements[0].amount, Uint128::from(1000u128));
+    assert_eq!(
+        result.requirements[1].skip_reason,
+        Some(CollectFeeSkipReason::CollectionCapReached)
+    );
+    assert_eq!(
+        cw20.query_balance(&app, router.addr()).balance,
+        Uint128::from(1000u128)
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::OptInCollection {
+            limits: ApproverLimits {
+                max_per_collection: None,
+                min_balance: None,
+                allowed_ask_assets: None,
+                cooldown: Some(60),
+            },
+        },
+        &[],
+    )
+    .unwrap();
+
+    // the cooldown started by the first requirement holds back the second one
+    app.update_block(|block| block.time = block.time.plus_seconds(61));
+    let result = collect_cw20_fees_batch(&mut app, &treasury, &cw20, &usdc, 2);
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Collected);
+    assert_eq!(
+        result.requirements[1].skip_reason,
+        Some(CollectFeeSkipReason::ApproverCooldown)
+    );
+    assert_eq!(
+        cw20.query_balance(&app, router.addr()).balance,
+        Uint128::from(INITIAL_BALANCE)
+    );
+}
+
+#[test]
+fn test_collect_fees_native_reserve() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, router, usdc) = mock_app();
+
+    let reserves: NativeReservesResponse = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::NativeReserves {})
+        .unwrap();
+    assert_eq!(
+        reserves.0,
+        vec![NativeReserve {
+            denom: "orai".to_string(),
+            amount: Uint128::from(1000000u128),
+            percent: None,
+        }]
+    );
+
+    let new_reserve = NativeReserve {
+        denom: "orai".to_string(),
+        amount: Uint128::from(5000000u128),
+        percent: None,
+    };
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateNativeReserves {
+                reserves: vec![new_reserve.clone()],
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateNativeReserves {
+            reserves: vec![new_reserve],
+        },
+        &[],
+    )
+    .unwrap();
+
+    // act
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::CollectFees {
+            collect_fee_requirements: vec![CollectFeeRequirement {
+                approver: owner.clone(),
+                swap_operations: vec![SwapOperation::SwapV3 {
+                    pool_key: PoolKey {
+                        token_x: "orai".into(),
+                        token_y: usdc.addr().to_string(),
+                        fee_tier: FeeTier {
+                            fee: Percentage(3u64),
+                            tick_spacing: 100,
+                        },
+                    },
+                    x_to_y: true,
+                }],
+                minimum_receive: None,
+                router: None,
+                source: None,
+                fee_receiver: None,
+                label: None,
+            }],
+            auto_distribute: None,
+            mode: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // assert
+    let native_balance = app.wrap().query_balance(router.addr(), "orai").unwrap();
+    assert_eq!(
+        native_balance.amount,
+        Uint128::from(INITIAL_BALANCE)
+            .checked_sub(Uint128::from(5000000u128))
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_collect_fees_slippage_protection() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(INITIAL_BALANCE),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateMaxSlippage {
+                max_slippage_bps: Some(10001),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidSlippage {}
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateMaxSlippage {
+            max_slippage_bps: Some(100),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // act
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+
+    // assert: the router simulates 1:1, so 1% slippage leaves 99% of the balance
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Collected);
+    assert_eq!(
+        result.requirements[0].minimum_receive,
+        Some(Uint128::from(INITIAL_BALANCE / 100 * 99))
+    );
+}
+
+#[test]
+fn test_collect_fees_limited_by_cw20_allowance() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, router, usdc) = mock_app();
+
+    // act
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+
+    // assert
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::InsufficientAllowance)
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(result.requirements[0].amount, Uint128::from(1000u128));
+    assert_eq!(
+        cw20.query_balance(&app, router.addr()).balance,
+        Uint128::from(1000u128)
+    );
+}
+
+#[test]
+fn test_collection_history_records_received_amounts() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // act
+    let response = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![
+                    CollectFeeRequirement {
+                        approver: owner.clone(),
+                        swap_operations: vec![SwapOperation::SwapV3 {
+                            pool_key: PoolKey {
+                                token_x: "orai".into(),
+                                token_y: usdc.addr().to_string(),
+                                fee_tier: FeeTier {
+                                    fee: Percentage(3u64),
+                                    tick_spacing: 100,
+                                },
+                            },
+                            x_to_y: true,
+                        }],
+                        minimum_receive: None,
+                        router: None,
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                    CollectFeeRequirement {
+                        approver: owner.clone(),
+                        swap_operations: vec![SwapOperation::SwapV3 {
+                            pool_key: PoolKey {
+                                token_x: cw20.addr().to_string(),
+                                token_y: usdc.addr().to_string(),
+                                fee_tier: FeeTier {
+                                    fee: Percentage(3u64),
+                                    tick_spacing: 100,
+                                },
+                            },
+                            x_to_y: true,
+                        }],
+                        minimum_receive: None,
+                        router: None,
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                ],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // assert
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(result.requirements[0].collection_id, Some(1));
+    assert_eq!(result.requirements[1].collection_id, Some(2));
+
+    let history: CollectionHistoryResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::CollectionHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(history.0.len(), 2);
+    assert_eq!(
+        history.0[0].offer_asset,
+        AssetInfo::NativeToken {
+            denom: "orai".to_string()
+        }
+    );
+    assert_eq!(
+        history.0[0].received,
+        Uint128::from(INITIAL_BALANCE)
+            .checked_sub(Uint128::from(1000000u128))
+            .unwrap()
+    );
+    assert_eq!(history.0[1].offer_amount, Uint128::from(1000u128));
+    assert_eq!(history.0[1].received, Uint128::from(1000u128));
+
+    let proceeds_events = response
+        .events
+        .iter()
+        .filter(|event| event.ty == "wasm-collection_proceeds")
+        .count();
+    assert_eq!(proceeds_events, 2);
+}
+
+#[test]
+fn test_collect_fees_auto_distribute() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, cw20, ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // act
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::CollectFees {
+            collect_fee_requirements: vec![CollectFeeRequirement {
+                approver: owner.clone(),
+                swap_operations: vec![SwapOperation::SwapV3 {
+                    pool_key: PoolKey {
+                        token_x: cw20.addr().to_string(),
+                        token_y: usdc.addr().to_string(),
+                        fee_tier: FeeTier {
+                            fee: Percentage(3u64),
+                            tick_spacing: 100,
+                        },
+                    },
+                    x_to_y: true,
+                }],
+                minimum_receive: None,
+                router: None,
+                source: None,
+                fee_receiver: None,
+                label: None,
+            }],
+            auto_distribute: Some(true),
+            mode: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // assert
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        Uint128::zero()
+    );
+    assert_eq!(
+        usdc.query_balance(&app, ping_pong.addr()).balance,
+        Uint128::from(400u128)
+    );
+    assert_eq!(
+        usdc.query_balance(&app, &finance).balance,
+        Uint128::from(600u128)
+    );
+}
+
+#[test]
+fn test_collect_fees_pays_keeper_bounty() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, cw20, ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateKeeperBounty {
+                keeper_bounty: Some(KeeperBounty {
+                    bps: 10001,
+                    max_per_call: Uint128::from(50u128),
+                }),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidKeeperBounty {}
+    );
+
+    // 10% of the proceeds, capped at 50 per run
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateKeeperBounty {
+            keeper_bounty: Some(KeeperBounty {
+                bps: 1000,
+                max_per_call: Uint128::from(50u128),
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+    let owner_balance = usdc.query_balance(&app, &owner).balance;
+
+    // act
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::CollectFees {
+            collect_fee_requirements: vec![CollectFeeRequirement {
+                approver: owner.clone(),
+                swap_operations: vec![SwapOperation::SwapV3 {
+                    pool_key: PoolKey {
+                        token_x: cw20.addr().to_string(),
+                        token_y: usdc.addr().to_string(),
+                        fee_tier: FeeTier {
+                            fee: Percentage(3u64),
+                            tick_spacing: 100,
+                        },
+                    },
+                    x_to_y: true,
+                }],
+                minimum_receive: None,
+                router: None,
+                source: None,
+                fee_receiver: None,
+                label: None,
+            }],
+            auto_distribute: Some(true),
+            mode: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // assert
+    assert_eq!(
+        usdc.query_balance(&app, &owner).balance,
+        owner_balance + Uint128::from(50u128)
+    );
+    assert_eq!(
+        usdc.query_balance(&app, ping_pong.addr()).balance,
+        Uint128::from(380u128)
+    );
+    assert_eq!(
+        usdc.query_balance(&app, &finance).balance,
+        Uint128::from(570u128)
+    );
+}
+
+#[test]
+fn test_permissionless_collection() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let keeper = Addr::unchecked("keeper");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(INITIAL_BALANCE),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::RegisterApprover {
+            approver: owner.clone(),
+            routes: vec![ApproverRoute {
+                swap_operations: vec![SwapOperation::SwapV3 {
+                    pool_key: PoolKey {
+                        token_x: cw20.addr().to_string(),
+                        token_y: usdc.addr().to_string(),
+                        fee_tier: FeeTier {
+                            fee: Percentage(3u64),
+                            tick_spacing: 100,
+                        },
+                    },
+                    x_to_y: true,
+                }],
+                fee_receiver: None,
+                label: None,
+                router: None,
+            }],
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::OptInCollection {
+            limits: ApproverLimits {
+                max_per_collection: Some(Uint128::from(1000u128)),
+                min_balance: None,
+                allowed_ask_assets: None,
+                cooldown: None,
+            },
+        },
+        &[],
+    )
+    .unwrap();
+
+    // routes supplied by the keeper are never used
+    let collect_msg = ExecuteMsg::CollectFees {
+        collect_fee_requirements: vec![
+            CollectFeeRequirement {
+                approver: owner.clone(),
+                swap_operations: vec![],
+                minimum_receive: None,
+                router: None,
+                source: None,
+                fee_receiver: None,
+                label: None,
+            },
+            CollectFeeRequirement {
+                approver: Addr::unchecked("finance"),
+                swap_operations: vec![],
+                minimum_receive: None,
+                router: None,
+                source: None,
+                fee_receiver: None,
+                label: None,
+            },
+        ],
+        auto_distribute: None,
+        mode: None,
+    };
+
+    let err = app
+        .execute_contract(keeper.clone(), treasury.addr().clone(), &collect_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    let err = app
+        .execute_contract(
+            keeper.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdatePermissionlessMode {
+                permissionless: Some(PermissionlessMode { cooldown: 3600 }),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    // keepers can not be trusted with the minimum receive
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdatePermissionlessMode {
+                permissionless: Some(PermissionlessMode { cooldown: 3600 }),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::MissingSlippageProtection {}
+    );
+
+    // nor with the quote the max slippage is applied to
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateMaxSlippage {
+            max_slippage_bps: Some(100),
+        },
+        &[],
+    )
+    .unwrap();
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdatePermissionlessMode {
+                permissionless: Some(PermissionlessMode { cooldown: 3600 }),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::MissingSlippageProtection {}
+    );
+
+    let update_min_output = |app: &mut StargateAccpetingModuleApp, min_output: Option<u128>| {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateCollectThresholds {
+                thresholds: vec![CollectThreshold {
+                    asset_info: AssetInfo::Token {
+                        contract_addr: cw20.addr().clone(),
+                    },
+                    min_amount: Uint128::zero(),
+                    min_output: min_output.map(Uint128::from),
+                }],
+            },
+            &[],
+        )
+        .unwrap();
+    };
+    update_min_output(&mut app, Some(1));
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdatePermissionlessMode {
+            permissionless: Some(PermissionlessMode { cooldown: 3600 }),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // act
+    let response = app
+        .execute_contract(keeper.clone(), treasury.addr().clone(), &collect_msg, &[])
+        .unwrap();
+
+    // assert
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(result.requirements.len(), 1);
+    assert_eq!(result.requirements[0].approver, owner);
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Collected);
+
+    // keepers do not get executor stats
+    let stats: ExecutorStatsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::ExecutorStats {
+                addr: keeper.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(stats.0, None);
+
+    // the same approver can not be collected again before the cooldown
+    let response = app
+        .execute_contract(
+            keeper.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectRegisteredFees {
+                approvers: None,
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::ApproverCooldown)
+    );
+
+    // executors are not bound by the permissionless cooldown
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Collected);
+
+    app.update_block(|block| block.time = block.time.plus_seconds(3600));
+    let response = app
+        .execute_contract(
+            keeper.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectRegisteredFees {
+                approvers: None,
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Collected);
+
+    // without a minimum output the swaps of keepers are refused, even with a max slippage
+    update_min_output(&mut app, None);
+    app.update_block(|block| block.time = block.time.plus_seconds(3600));
+    let response = app
+        .execute_contract(
+            keeper.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectRegisteredFees {
+                approvers: None,
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::NoSlippageProtection)
+    );
+}
+
+#[test]
+fn test_collect_fees_router_registry() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let backup = RouterInfo {
+        name: "backup".to_string(),
+        addr: Addr::unchecked("backup_router"),
+        kind: RouterKind::MixedRouter,
+        paused: true,
+    };
+    let v3 = RouterInfo {
+        name: "v3".to_string(),
+        addr: Addr::unchecked("v3_pool"),
+        kind: RouterKind::OraiswapV3,
+        paused: false,
+    };
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateRouter {
+                router: backup.clone(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    for registered in [backup.clone(), v3.clone()] {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateRouter { router: registered },
+            &[],
+        )
+        .unwrap();
+    }
+
+    let routers: RoutersResponse = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::Routers {})
+        .unwrap();
+    assert_eq!(routers.0, vec![backup, v3]);
+
+    let hop = SwapOperation::SwapV3 {
+        pool_key: PoolKey {
+            token_x: cw20.addr().to_string(),
+            token_y: usdc.addr().to_string(),
+            fee_tier: FeeTier {
+                fee: Percentage(3u64),
+                tick_spacing: 100,
+            },
+        },
+        x_to_y: true,
+    };
+
+    // act
+    let response = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![
+                    CollectFeeRequirement {
+                        approver: owner.clone(),
+                        swap_operations: vec![hop.clone()],
+                        minimum_receive: None,
+                        router: Some("missing".to_string()),
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                    // the pool contract only swaps a single hop
+                    CollectFeeRequirement {
+                        approver: owner.clone(),
+                        swap_operations: vec![hop.clone(), hop.clone()],
+                        minimum_receive: None,
+                        router: Some("v3".to_string()),
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                    // a paused router is never replaced by another one
+                    CollectFeeRequirement {
+                        approver: owner.clone(),
+                        swap_operations: vec![hop.clone()],
+                        minimum_receive: None,
+                        router: Some("backup".to_string()),
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                    CollectFeeRequirement {
+                        approver: owner.clone(),
+                        swap_operations: vec![hop],
+                        minimum_receive: None,
+                        router: None,
+                        source: None,
+                        fee_receiver: None,
+                        label: None,
+                    },
+                ],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // assert
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::UnknownRouter)
+    );
+    assert_eq!(
+        result.requirements[1].skip_reason,
+        Some(CollectFeeSkipReason::UnsupportedSwapOperation)
+    );
+    assert_eq!(
+        result.requirements[2].skip_reason,
+        Some(CollectFeeSkipReason::RouterPaused)
+    );
+    assert_eq!(result.requirements[3].status, CollectFeeStatus::Collected);
+    assert_eq!(result.requirements[3].router, Some(router.addr().clone()));
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        Uint128::from(1000u128)
+    );
+}
+
+#[test]
+fn test_collect_fees_claims_v3_position_fees() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let claim_requirement = |position_indexes: Vec<u32>| CollectFeeRequirement {
+        approver: owner.clone(),
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x: cw20.addr().to_string(),
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        minimum_receive: None,
+        router: None,
+        source: Some(CollectSource::V3PositionFees {
+            contract: Addr::unchecked("oraiswap_v3"),
+            position_indexes,
+        }),
+        fee_receiver: None,
+        label: None,
+    };
+
+    // act
+    let response = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![
+                    claim_requirement(vec![]),
+                    claim_requirement(vec![0, 1]),
+                ],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // assert
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::NothingToClaim)
+    );
+    assert_eq!(result.requirements[1].status, CollectFeeStatus::Claimed);
+
+    // the claimed fees are swapped in the same run
+    let history: CollectionHistoryResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::CollectionHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(history.0.len(), 1);
+    assert_eq!(history.0[0].id, 2);
+    assert_eq!(history.0[0].received, Uint128::from(1000u128));
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        Uint128::from(1000u128)
+    );
+}
+
+#[test]
+fn test_collect_fees_claims_keep_approver_limits_across_batch() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(INITIAL_BALANCE),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::OptInCollection {
+            limits: ApproverLimits {
+                max_per_collection: Some(Uint128::from(1000u128)),
+                min_balance: None,
+                allowed_ask_assets: None,
+                cooldown: None,
+            },
+        },
+        &[],
+    )
+    .unwrap();
+
+    let requirement = |source: Option<CollectSource>| CollectFeeRequirement {
+        approver: owner.clone(),
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x: cw20.addr().to_string(),
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        minimum_receive: None,
+        router: None,
+        source,
+        fee_receiver: None,
+        label: None,
+    };
+
+    // act: the plain requirement is queued after the claim, but pulls before its swap
+    let response = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![
+                    requirement(Some(CollectSource::V3PositionFees {
+                        contract: Addr::unchecked("oraiswap_v3"),
+                        position_indexes: vec![0],
+                    })),
+                    requirement(None),
+                ],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // assert: the swap of the claim finds the cap used up by the plain requirement
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Claimed);
+    assert_eq!(result.requirements[1].amount, Uint128::from(1000u128));
+    assert!(response.events.iter().any(|event| {
+        event.attributes.iter().any(|attr| {
+            attr.key == "skip_reason"
+                && attr.value == CollectFeeSkipReason::CollectionCapReached.to_string()
+        })
+    }));
+    assert_eq!(
+        cw20.query_balance(&app, router.addr()).balance,
+        Uint128::from(1000u128)
+    );
+}
+
+#[test]
+fn test_collect_fees_claims_staking_rewards() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+
+    // act
+    let response = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![CollectFeeRequirement {
+                    approver: owner.clone(),
+                    swap_operations: vec![SwapOperation::SwapV3 {
+                        pool_key: PoolKey {
+                            token_x: "orai".into(),
+                            token_y: usdc.addr().to_string(),
+                            fee_tier: FeeTier {
+                                fee: Percentage(3u64),
+                                tick_spacing: 100,
+                            },
+                        },
+                        x_to_y: true,
+                    }],
+                    minimum_receive: None,
+                    router: None,
+                    source: Some(CollectSource::StakingRewards {
+                        validators: vec!["oraivaloper1validator".to_string()],
+                        validator_commission: Some("oraivaloper1validator".to_string()),
+                    }),
+                    fee_receiver: None,
+                    label: None,
+                }],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // assert
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Claimed);
+
+    // the claimed orai is swapped like any native fee, above the gas reserve
+    let history: CollectionHistoryResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::CollectionHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(history.0.len(), 1);
+    assert_eq!(
+        history.0[0].offer_amount,
+        Uint128::from(INITIAL_BALANCE)
+            .checked_sub(Uint128::from(1000000u128))
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_collect_fees_in_chunks() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+    let cw20_asset = AssetInfo::Token {
+        contract_addr: cw20.addr().clone(),
+    };
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateChunkLimits {
+            limits: vec![ChunkLimit {
+                asset_info: cw20_asset.clone(),
+                max_per_block: Uint128::from(400u128),
+            }],
+        },
+        &[],
+    )
+    .unwrap();
+
+    // thresholds apply to the whole collectable amount, not to a single chunk
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateCollectThresholds {
+            thresholds: vec![CollectThreshold {
+                asset_info: cw20_asset.clone(),
+                min_amount: Uint128::from(500u128),
+                min_output: Some(Uint128::from(500u128)),
+            }],
+        },
+        &[],
+    )
+    .unwrap();
+
+    let queued_remainder = |app: &StargateAccpetingModuleApp| -> Uint128 {
+        app.wrap()
+            .query_wasm_smart::<QueuedRemainderResponse>(
+                treasury.addr(),
+                &QueryMsg::QueuedRemainder {
+                    approver: owner.clone(),
+                    asset_info: cw20_asset.clone(),
+                },
+            )
+            .unwrap()
+            .0
+    };
+
+    // act
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+
+    // assert
+    assert_eq!(result.requirements[0].amount, Uint128::from(400u128));
+    assert_eq!(queued_remainder(&app), Uint128::from(600u128));
+
+    // the chunk of the block is already spent
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::ChunkLimitReached)
+    );
+
+    app.update_block(|block| block.height += 1);
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(result.requirements[0].amount, Uint128::from(400u128));
+    assert_eq!(queued_remainder(&app), Uint128::from(200u128));
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        Uint128::from(800u128)
+    );
+
+    // removing the limit drops the remainders it queued
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateChunkLimits {
+            limits: vec![ChunkLimit {
+                asset_info: cw20_asset.clone(),
+                max_per_block: Uint128::zero(),
+            }],
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(queued_remainder(&app), Uint128::zero());
+}
+
+#[test]
+fn test_collect_fees_skips_below_thresholds() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let update_threshold =
+        |app: &mut StargateAccpetingModuleApp, min_amount: u128, min_output: Option<u128>| {
+            app.execute_contract(
+                owner.clone(),
+                treasury.addr().clone(),
+                &ExecuteMsg::UpdateCollectThresholds {
+                    thresholds: vec![CollectThreshold {
+                        asset_info: AssetInfo::Token {
+                            contract_addr: cw20.addr().clone(),
+                        },
+                        min_amount: Uint128::from(min_amount),
+                        min_output: min_output.map(Uint128::from),
+                    }],
+                },
+                &[],
+            )
+            .unwrap();
+        };
+
+    // act & assert
+    update_threshold(&mut app, 2000, None);
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::BelowThreshold)
+    );
+
+    // the router quotes 1:1
+    update_threshold(&mut app, 100, Some(5000));
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::BelowMinOutput)
+    );
+
+    update_threshold(&mut app, 100, Some(500));
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Collected);
+}
+
+#[test]
+fn test_collect_fees_offer_asset_policy() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+    let cw20_asset = AssetInfo::Token {
+        contract_addr: cw20.addr().clone(),
+    };
+    let orai_asset = AssetInfo::NativeToken {
+        denom: "orai".to_string(),
+    };
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateDeniedOfferAssets {
+                assets: vec![cw20_asset.clone()],
+                denied: true,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDeniedOfferAssets {
+            assets: vec![cw20_asset.clone()],
+            denied: true,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // act
+    let response = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![CollectFeeRequirement {
+                    approver: owner.clone(),
+                    swap_operations: vec![SwapOperation::SwapV3 {
+                        pool_key: PoolKey {
+                            token_x: cw20.addr().to_string(),
+                            token_y: usdc.addr().to_string(),
+                            fee_tier: FeeTier {
+                                fee: Percentage(3u64),
+                                tick_spacing: 100,
+                            },
+                        },
+                        x_to_y: true,
+                    }],
+                    minimum_receive: None,
+                    router: None,
+                    source: None,
+                    fee_receiver: None,
+                    label: None,
+                }],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // assert
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Rejected);
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::OfferAssetDenied)
+    );
+    let rejected = response
+        .events
+        .iter()
+        .filter(|event| event.ty == "wasm-collect_fee")
+        .flat_map(|event| event.attributes.iter())
+        .any(|attr| attr.key == "status" && attr.value == "rejected");
+    assert!(rejected);
+
+    // a non empty allowlist rejects every other asset
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDeniedOfferAssets {
+            assets: vec![cw20_asset.clone()],
+            denied: false,
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateAllowedOfferAssets {
+            assets: vec![orai_asset.clone()],
+            allowed: true,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let denied: OfferAssetsResponse = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::DeniedOfferAssets {})
+        .unwrap();
+    assert!(denied.0.is_empty());
+    let allowed: OfferAssetsResponse = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::AllowedOfferAssets {})
+        .unwrap();
+    assert_eq!(allowed.0, vec![orai_asset]);
+
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::OfferAssetNotAllowed)
+    );
+}
+
+#[test]
+fn test_update_asset_registry() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, ..) = mock_app();
+    let entry = AssetRegistryEntry {
+        asset: "factory/creator/usdt".to_string(),
+        asset_info: Some(AssetInfo::NativeToken {
+            denom: "factory/creator/usdt".to_string(),
+        }),
+    };
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateAssetRegistry {
+                entries: vec![entry.clone()],
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    // act
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateAssetRegistry {
+            entries: vec![
+                entry.clone(),
+                AssetRegistryEntry {
+                    asset: "orai".to_string(),
+                    asset_info: None,
+                },
+            ],
+        },
+        &[],
+    )
+    .unwrap();
+
+    // assert
+    let registry: AssetRegistryResponse = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::AssetRegistry {})
+        .unwrap();
+    assert_eq!(registry.0, vec![entry]);
+}
+
+#[test]
+fn test_collect_fees_best_effort() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // no contract is deployed behind the broken router, so its swaps fail
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateRouter {
+            router: RouterInfo {
+                name: "broken".to_string(),
+                addr: Addr::unchecked("broken_router"),
+                kind: RouterKind::MixedRouter,
+                paused: false,
+            },
+        },
+        &[],
+    )
+    .unwrap();
+
+    let hop = |token_x: String| SwapOperation::SwapV3 {
+        pool_key: PoolKey {
+            token_x,
+            token_y: usdc.addr().to_string(),
+            fee_tier: FeeTier {
+                fee: Percentage(3u64),
+                tick_spacing: 100,
+            },
+        },
+        x_to_y: true,
+    };
+    let collect_msg = |mode: CollectMode| ExecuteMsg::CollectFees {
+        collect_fee_requirements: vec![
+            CollectFeeRequirement {
+                approver: owner.clone(),
+                swap_operations: vec![hop("orai".to_string())],
+                minimum_receive: None,
+                router: Some("broken".to_string()),
+                source: None,
+                fee_receiver: None,
+                label: None,
+            },
+            CollectFeeRequirement {
+                approver: owner.clone(),
+                swap_operations: vec![hop(cw20.addr().to_string())],
+                minimum_receive: None,
+                router: None,
+                source: None,
+                fee_receiver: None,
+                label: None,
+            },
+        ],
+        auto_distribute: None,
+        mode: Some(mode),
+    };
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &collect_msg(CollectMode::Atomic),
+        &[],
+    )
+    .unwrap_err();
+    let orai_balance = app.wrap().query_balance(treasury.addr(), "orai").unwrap();
+
+    // act
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &collect_msg(CollectMode::BestEffort),
+        &[],
+    )
+    .unwrap();
+
+    // assert
+    let history: CollectionHistoryResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::CollectionHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(history.0.len(), 2);
+    assert_eq!(history.0[0].received, Uint128::zero());
+    assert!(history.0[0].error.is_some());
+    assert_eq!(history.0[1].received, Uint128::from(1000u128));
+    assert_eq!(history.0[1].error, None);
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        Uint128::from(1000u128)
+    );
+
+    // the failed swap reverted its pull, so the contract holds no extra orai
+    assert_eq!(
+        app.wrap().query_balance(treasury.addr(), "orai").unwrap(),
+        orai_balance
+    );
+}
+
+#[test]
+fn test_retry_failed_collections() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, router, usdc) = mock_app();
+    let broken = |addr: Addr| ExecuteMsg::UpdateRouter {
+        router: RouterInfo {
+            name: "broken".to_string(),
+            addr,
+            kind: RouterKind::MixedRouter,
+            paused: false,
+        },
+    };
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &broken(Addr::unchecked("broken_router")),
+        &[],
+    )
+    .unwrap();
+
+    let requirement = CollectFeeRequirement {
+        approver: owner.clone(),
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x: "orai".into(),
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        minimum_receive: None,
+        router: Some("broken".to_string()),
+        source: None,
+        fee_receiver: None,
+        label: None,
+    };
+    // a second route of the same offer asset is queued on its own
+    let other_requirement = CollectFeeRequirement {
+        minimum_receive: Some(Uint128::one()),
+        ..requirement.clone()
+    };
+    let collect_msg = ExecuteMsg::CollectFees {
+        collect_fee_requirements: vec![requirement.clone(), other_requirement.clone()],
+        auto_distribute: None,
+        mode: Some(CollectMode::BestEffort),
+    };
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &collect_msg, &[])
+        .unwrap();
+
+    let query_failed = |app: &StargateAccpetingModuleApp| -> FailedCollectionsResponse {
+        app.wrap()
+            .query_wasm_smart(
+                treasury.addr(),
+                &QueryMsg::FailedCollections {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+    };
+    let failed = query_failed(&app);
+    assert_eq!(failed.0.len(), 2);
+    assert_eq!(failed.0[0].requirement, requirement);
+    assert_eq!(failed.0[0].attempts, 1);
+    assert_eq!(failed.0[1].requirement, other_requirement);
+
+    let remove_msg = ExecuteMsg::RemoveFailedCollections {
+        approver: owner.clone(),
+        ids: Some(vec![failed.0[1].id]),
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &remove_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &remove_msg, &[])
+        .unwrap();
+    assert_eq!(query_failed(&app).0.len(), 1);
+
+    let retry_msg = ExecuteMsg::RetryFailedCollections { limit: None };
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &retry_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    // act
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &retry_msg, &[])
+        .unwrap();
+    assert_eq!(query_failed(&app).0[0].attempts, 2);
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &broken(router.addr().clone()),
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &retry_msg, &[])
+        .unwrap();
+
+    // assert
+    assert!(query_failed(&app).0.is_empty());
+    let history: CollectionHistoryResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::CollectionHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(history.0.len(), 4);
+    assert_eq!(history.0[3].error, None);
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        history.0[3].received
+    );
+
+    // a requirement failing too often leaves the queue
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &broken(Addr::unchecked("broken_router")),
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &collect_msg, &[])
+        .unwrap();
+    for _ in 1..4 {
+        app.execute_contract(owner.clone(), treasury.addr().clone(), &retry_msg, &[])
+            .unwrap();
+    }
+    let failed = query_failed(&app);
+    assert_eq!(failed.0.len(), 2);
+    assert!(failed.0.iter().all(|failed| failed.attempts == 4));
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &retry_msg, &[])
+        .unwrap();
+    assert!(query_failed(&app).0.is_empty());
+
+    // skipped retries are attempts as well
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &collect_msg, &[])
+        .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::OptOutCollection {},
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.height += 1);
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &retry_msg, &[])
+        .unwrap();
+    let failed = query_failed(&app);
+    let block = app.block_info();
+    assert_eq!(failed.0.len(), 2);
+    for failed in failed.0 {
+        assert_eq!(failed.attempts, 2);
+        assert_eq!(failed.error, "approver_opted_out");
+        assert_eq!(failed.last_try_height, block.height);
+    }
+    for _ in 0..3 {
+        app.execute_contract(owner.clone(), treasury.addr().clone(), &retry_msg, &[])
+            .unwrap();
+    }
+    assert!(query_failed(&app).0.is_empty());
+}
+
+#[test]
+fn test_collect_fees_to_fee_receiver() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let lp_bucket = Addr::unchecked("lp_bucket");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let update_msg = ExecuteMsg::UpdateFeeReceivers {
+        receivers: vec![FeeReceiver {
+            name: "lp".to_string(),
+            addr: Some(lp_bucket.clone()),
+        }],
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &update_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &update_msg, &[])
+        .unwrap();
+
+    let receivers: FeeReceiversResponse = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::FeeReceivers {})
+        .unwrap();
+    assert_eq!(
+        receivers.0,
+        vec![FeeReceiver {
+            name: "lp".to_string(),
+            addr: Some(lp_bucket.clone()),
+        }]
+    );
+
+    let requirement = |token_x: String, fee_receiver: Option<&str>| CollectFeeRequirement {
+        approver: owner.clone(),
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x,
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        minimum_receive: None,
+        router: None,
+        source: None,
+        fee_receiver: fee_receiver.map(str::to_string),
+        label: None,
+    };
+
+    // act
+    let response = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![
+                    requirement("orai".to_string(), Some("missing")),
+                    requirement(cw20.addr().to_string(), Some("lp")),
+                    requirement("orai".to_string(), None),
+                ],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // assert
+    let result = from_json::<CollectFeesResult>(&response.data.unwrap()).unwrap();
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::UnknownFeeReceiver)
+    );
+
+    let history: CollectionHistoryResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::CollectionHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(history.0.len(), 2);
+    assert_eq!(history.0[0].fee_receiver, Some(lp_bucket.clone()));
+    assert_eq!(history.0[0].received, Uint128::from(1000u128));
+    assert_eq!(history.0[1].fee_receiver, None);
+
+    // the bucket receives its proceeds, the contract keeps the others
+    assert_eq!(
+        usdc.query_balance(&app, &lp_bucket).balance,
+        Uint128::from(1000u128)
+    );
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        history.0[1].received
+    );
+}
+
+#[test]
+fn test_collect_fees_respects_retentions() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+    let cw20_asset = AssetInfo::Token {
+        contract_addr: cw20.addr().clone(),
+    };
+    let balance = cw20.query_balance(&app, &owner).balance;
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: balance,
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let retention = AssetRetention {
+        asset_info: cw20_asset.clone(),
+        amount: balance - Uint128::from(400u128),
+        percent: None,
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateRetentions {
+                approver: owner.clone(),
+                retentions: vec![retention.clone()],
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    // retentions are only kept for valid approver addresses
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateRetentions {
+                approver: Addr::unchecked("OWNER"),
+                retentions: vec![retention.clone()],
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Std(_)
+    ));
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateRetentions {
+                approver: owner.clone(),
+                retentions: vec![AssetRetention {
+                    percent: Some(Decimal::percent(101)),
+                    ..retention.clone()
+                }],
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidRetention {
+            asset: cw20_asset.to_string()
+        }
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateRetentions {
+            approver: owner.clone(),
+            retentions: vec![retention.clone()],
+        },
+        &[],
+    )
+    .unwrap();
+
+    let retentions: RetentionsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::Retentions {
+                approver: owner.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(retentions.0, vec![retention]);
+
+    // act
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+
+    // assert
+    assert_eq!(result.requirements[0].amount, Uint128::from(400u128));
+    assert_eq!(
+        cw20.query_balance(&app, &owner).balance,
+        balance - Uint128::from(400u128)
+    );
+}
+
+#[test]
+fn test_executor_quota() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let update_quota = |app: &mut StargateAccpetingModuleApp, quota: ExecutorQuota| {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateExecutorQuota {
+                executor: owner.clone(),
+                quota: Some(quota),
+            },
+            &[],
+        )
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateExecutorQuota {
+                executor: owner.clone(),
+                quota: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    let collect_msg = ExecuteMsg::CollectRegisteredFees {
+        approvers: Some(vec![]),
+        auto_distribute: None,
+        mode: None,
+    };
+    let quota_exceeded = ContractError::ExecutorQuotaExceeded {
+        executor: owner.to_string(),
+    };
+
+    // act & assert
+    let err = update_quota(
+        &mut app,
+        ExecutorQuota {
+            window: 0,
+            max_calls: Some(1),
+            max_value: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidExecutorQuota {}
+    );
+
+    // a call offering more than the value left in the window is reverted
+    update_quota(
+        &mut app,
+        ExecutorQuota {
+            window: 60,
+            max_calls: None,
+            max_value: Some(Uint128::from(500u128)),
+        },
+    )
+    .unwrap();
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![CollectFeeRequirement {
+                    approver: owner.clone(),
+                    swap_operations: vec![SwapOperation::SwapV3 {
+                        pool_key: PoolKey {
+                            token_x: cw20.addr().to_string(),
+                            token_y: usdc.addr().to_string(),
+                            fee_tier: FeeTier {
+                                fee: Percentage(3u64),
+                                tick_spacing: 100,
+                            },
+                        },
+                        x_to_y: true,
+                    }],
+                    minimum_receive: None,
+                    router: None,
+                    source: None,
+                    fee_receiver: None,
+                    label: None,
+                }],
+                auto_distribute: None,
+                mode: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(err.downcast::<ContractError>().unwrap(), quota_exceeded);
+
+    update_quota(
+        &mut app,
+        ExecutorQuota {
+            window: 60,
+            max_calls: Some(1),
+            max_value: None,
+        },
+    )
+    .unwrap();
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(result.requirements[0].amount, Uint128::from(1000u128));
+
+    let quota: ExecutorQuotaResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::ExecutorQuota {
+                executor: owner.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(quota.remaining_calls, Some(0));
+    assert_eq!(quota.remaining_value, None);
+
+    let err = app
+        .execute_contract(owner.clone(), treasury.addr().clone(), &collect_msg, &[])
+        .unwrap_err();
+    assert_eq!(err.downcast::<ContractError>().unwrap(), quota_exceeded);
+
+    // the calls are available again in the next window
+    app.update_block(|block| block.time = block.time.plus_seconds(60));
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &collect_msg, &[])
+        .unwrap();
+}
+
+#[test]
+fn test_executor_stats() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // act
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Collected);
+    // the allowance is spent, the second requirement is skipped
+    let result = collect_cw20_fees(&mut app, &treasury, &cw20, &usdc);
+    assert_eq!(result.requirements[0].status, CollectFeeStatus::Skipped);
+
+    // assert
+    let stats: ExecutorStatsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::ExecutorStats {
+                addr: owner.clone(),
+            },
+        )
+        .unwrap();
+    let block = app.block_info();
+    assert_eq!(
+        stats.0,
+        Some(ExecutorStats {
+            executor: owner.clone(),
+            calls: 2,
+            requirements: 2,
+            skipped: 1,
+            last_call_height: block.height,
+            last_call_time: block.time,
+            total_proceeds: Uint128::from(1000u128),
+        })
+    );
+
+    let all_stats: AllExecutorStatsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::AllExecutorStats {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(all_stats.0, vec![stats.0.unwrap()]);
+
+    let stats: ExecutorStatsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::ExecutorStats {
+                addr: Addr::unchecked("not_executor"),
+            },
+        )
+        .unwrap();
+    assert_eq!(stats.0, None);
+}
+
+#[test]
+fn test_revenue_by_source() {
+    // arrange
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(1000u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let collect_msg = |label: &str| ExecuteMsg::CollectFees {
+        collect_fee_requirements: vec![CollectFeeRequirement {
+            approver: owner.clone(),
+            swap_operations: vec![SwapOperation::SwapV3 {
+                pool_key: PoolKey {
+                    token_x: cw20.addr().to_string(),
+                    token_y: usdc.addr().to_string(),
+                    fee_tier: FeeTier {
+                        fee: Percentage(3u64),
+                        tick_spacing: 100,
+                    },
+                },
+                x_to_y: true,
+            }],
+            minimum_receive: None,
+            router: None,
+            source: None,
+            fee_receiver: None,
+            label: Some(label.to_string()),
+        }],
+        auto_distribute: None,
+        mode: None,
+    };
+
+    let update_msg = ExecuteMsg::UpdateRevenueLabels {
+        labels: vec!["dex".to_string()],
+        listed: true,
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked("not_owner"),
+            treasury.addr().clone(),
+            &update_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &update_msg, &[])
+        .unwrap();
+
+    // an unlisted label is skipped before anything is pulled
+    let res = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &collect_msg("unlisted"),
+            &[],
+        )
+        .unwrap();
+    let result = from_json::<CollectFeesResult>(&res.data.unwrap()).unwrap();
+    assert_eq!(
+        result.requirements[0].skip_reason,
+        Some(CollectFeeSkipReason::UnknownLabel)
+    );
+
+    // act
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &collect_msg("dex"),
+        &[],
+    )
+    .unwrap();
+
+    // assert
+    let now = app.block_info().time;
+    let revenue: RevenueBySourceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::RevenueBySource { from: now, to: now },
+        )
+        .unwrap();
+    assert_eq!(
+        revenue.0,
+        vec![SourceRevenue {
+            label: "dex".to_string(),
+            amount: Uint128::from(1000u128),
+        }]
+    );
+
+    // the collection is outside of the following epochs
+    let revenue: RevenueBySourceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::RevenueBySource {
+                from: now.plus_days(1),
+                to: now.plus_days(2),
+            },
+        )
+        .unwrap();
+    assert_eq!(revenue.0, vec![]);
+
+    // a single query spans a bounded number of epochs
+    app.wrap()
+        .query_wasm_smart::<RevenueBySourceResponse>(
+            treasury.addr(),
+            &QueryMsg::RevenueBySource {
+                from: now,
+                to: now.plus_days(366),
+            },
+        )
+        .unwrap_err();
+}
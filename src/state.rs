@@ -1,6 +1,8 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Binary};
+use cosmwasm_std::{Addr, Binary, Decimal, Timestamp, Uint128};
 use cw_storage_plus::{Item, Map};
+use oraiswap::asset::AssetInfo;
+use oraiswap::mixed_router::SwapOperation;
 
 const CONFIG_KEY: &str = "config";
 const DISTRIBUTION_TARGET: &str = "distribution_target";
@@ -10,6 +12,59 @@ pub struct Config {
     pub owner: Addr,
     pub distribute_token: Addr,
     pub router: Option<Addr>,
+    /// when set, `minimum_receive` of collections is at least the router simulation minus this slippage
+    pub max_slippage_bps: Option<u16>,
+    /// query authz grants before pulling native fees, requires the grants query to be whitelisted.
+    /// Enabled unless the owner turns it off, so a missing grant skips the requirement instead of
+    /// reverting the whole batch
+    #[serde(default = "default_check_authz_grants")]
+    pub check_authz_grants: bool,
+    /// paid to the executor out of the proceeds of each collection run
+    pub keeper_bounty: Option<KeeperBounty>,
+    /// when set, anyone may collect the registered routes of registered approvers
+    pub permissionless: Option<PermissionlessMode>,
+    /// when set, executors may only collect the registered routes of registered approvers,
+    /// choosing nothing but the minimum receive and the claimed source
+    #[serde(default)]
+    pub registered_routes_only: bool,
+}
+
+fn default_check_authz_grants() -> bool {
+    true
+}
+
+#[cw_serde]
+pub struct KeeperBounty {
+    /// share of the received distribute token, in bps
+    pub bps: u16,
+    /// cap of the bounty paid for a single collection run
+    pub max_per_call: Uint128,
+}
+
+#[cw_serde]
+pub enum RouterKind {
+    /// oraiswap mixed router, swapping through both v2 pairs and v3 pools
+    MixedRouter,
+    /// oraiswap-v3 contract, swapping through a single pool directly
+    OraiswapV3,
+    /// legacy oraiswap router, swapping through v2 pairs only
+    OraiswapRouter,
+}
+
+/// Router selectable by name in a `CollectFeeRequirement`
+#[cw_serde]
+pub struct RouterInfo {
+    pub name: String,
+    pub addr: Addr,
+    pub kind: RouterKind,
+    /// requirements selecting a paused router are skipped until it is resumed
+    pub paused: bool,
+}
+
+#[cw_serde]
+pub struct PermissionlessMode {
+    /// seconds before the same approver can be collected again by a non executor
+    pub cooldown: u64,
 }
 
 #[cw_serde]
@@ -19,6 +74,287 @@ pub struct DistributeTarget {
     pub msg_hook: Option<Binary>,
 }
 
+#[cw_serde]
+pub struct ApproverRoute {
+    pub swap_operations: Vec<SwapOperation>,
+    /// name of the fee receiver the proceeds are forwarded to, kept by the contract when not set
+    #[serde(default)]
+    pub fee_receiver: Option<String>,
+    /// product line the proceeds of the route are attributed to
+    #[serde(default)]
+    pub label: Option<String>,
+    /// name of the registered router the route is swapped through, the default router when not set
+    #[serde(default)]
+    pub router: Option<String>,
+}
+
+#[cw_serde]
+pub struct RegisteredApprover {
+    pub approver: Addr,
+    pub routes: Vec<ApproverRoute>,
+}
+
+/// Limits set by an approver on how its fees may be collected
+#[cw_serde]
+#[derive(Default)]
+pub struct ApproverLimits {
+    /// maximum amount of an offer asset pulled in a single collection call, across all its requirements
+    pub max_per_collection: Option<Uint128>,
+    /// balance of every offer asset left in the approver account
+    pub min_balance: Option<Uint128>,
+    /// assets the fees may be swapped through on the way to the distribute token, any when not set
+    pub allowed_ask_assets: Option<Vec<AssetInfo>>,
+    /// minimum seconds between two collections from the approver
+    pub cooldown: Option<u64>,
+}
+
+/// How the cw20 fees of an approver are pulled
+#[cw_serde]
+#[derive(Default)]
+pub enum Cw20PullMode {
+    /// `TransferFrom` and `SendFrom` within the allowance of the contract
+    #[default]
+    Allowance,
+    /// the approver executes `Transfer` and `Send` through an authz `MsgExecuteContract` grant
+    Authz,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct ApproverSettings {
+    pub opted_out: bool,
+    pub limits: ApproverLimits,
+    /// kept when the approver opts in again with new limits
+    #[serde(default)]
+    pub cw20_pull: Cw20PullMode,
+}
+
+/// Native balance left in every approver account, the larger of `amount`
+/// and `percent` of the balance is kept
+#[cw_serde]
+pub struct NativeReserve {
+    pub denom: String,
+    pub amount: Uint128,
+    pub percent: Option<Decimal>,
+}
+
+/// Balance of `asset_info` left in an approver account, the larger of `amount`
+/// and `percent` of the balance is kept. Native assets keep the largest of their retention,
+/// the native reserve of the denom and the approver's `min_balance`, they do not add up
+#[cw_serde]
+pub struct AssetRetention {
+    pub asset_info: AssetInfo,
+    pub amount: Uint128,
+    pub percent: Option<Decimal>,
+}
+
+/// Offer assets with a limit are swapped in chunks of at most `max_per_block`
+/// per block, across all collection runs
+#[cw_serde]
+pub struct ChunkLimit {
+    pub asset_info: AssetInfo,
+    pub max_per_block: Uint128,
+}
+
+/// Requirements collecting less than `min_amount` of the asset, or quoted
+/// less than `min_output` of the distribute token, are skipped
+#[cw_serde]
+pub struct CollectThreshold {
+    pub asset_info: AssetInfo,
+    pub min_amount: Uint128,
+    pub min_output: Option<Uint128>,
+}
+
+/// Amount of an offer asset already swapped in the block at `height`
+#[cw_serde]
+pub struct ChunkSpent {
+    pub height: u64,
+    pub amount: Uint128,
+}
+
+/// How a collection run handles a requirement whose messages fail
+#[cw_serde]
+#[derive(Default)]
+pub enum CollectMode {
+    /// a single failing message reverts every requirement of the call
+    #[default]
+    Atomic,
+    /// a failing requirement is recorded in the history with its error,
+    /// the other requirements are still collected. The pulls of a failing requirement
+    /// are reverted with its swap, so its fees stay in the approver account
+    BestEffort,
+}
+
+#[cw_serde]
+pub struct CollectFeeRequirement {
+    pub approver: Addr,
+    pub swap_operations: Vec<SwapOperation>,
+    pub minimum_receive: Option<Uint128>,
+    /// name of a registered router, the default router is used when not set
+    pub router: Option<String>,
+    /// fees claimed into the approver account before its balance is collected
+    pub source: Option<CollectSource>,
+    /// name of an allowed fee receiver the proceeds are forwarded to,
+    /// the contract keeps them when not set
+    pub fee_receiver: Option<String>,
+    /// product line the proceeds are attributed to in `RevenueBySource`
+    pub label: Option<String>,
+}
+
+/// Fees held outside the approver balance. The approver must grant the contract
+/// an authz authorization for the claim messages.
+#[cw_serde]
+pub enum CollectSource {
+    /// `ClaimFee` of oraiswap-v3 positions owned by the approver, through `MsgExecuteContract`
+    V3PositionFees {
+        contract: Addr,
+        position_indexes: Vec<u32>,
+    },
+    /// Delegation rewards and validator commission, paid to the withdraw address
+    /// of the approver which is expected to be the approver itself
+    StakingRewards {
+        /// validators the approver delegates to
+        validators: Vec<String>,
+        /// operator address of the validator run by the approver
+        validator_commission: Option<String>,
+    },
+}
+
+/// A collection waiting for its swap reply to be measured
+#[cw_serde]
+pub struct PendingCollection {
+    /// requirement being collected, kept to retry it should it fail
+    pub requirement: CollectFeeRequirement,
+    pub offer_asset: AssetInfo,
+    pub offer_amount: Uint128,
+    /// checked against the received amount for swaps that can not enforce it themselves
+    pub minimum_receive: Option<Uint128>,
+    /// the proceeds are forwarded to this receiver once measured
+    pub fee_receiver: Option<Addr>,
+}
+
+/// Distribute token actually received by a collection
+#[cw_serde]
+pub struct CollectionRecord {
+    pub id: u64,
+    pub approver: Addr,
+    pub offer_asset: AssetInfo,
+    pub offer_amount: Uint128,
+    pub received: Uint128,
+    pub height: u64,
+    pub time: Timestamp,
+    /// why the collection failed in best-effort mode, a swap that executed below its
+    /// minimum receive keeps its `received`
+    #[serde(default)]
+    pub error: Option<String>,
+    /// receiver the proceeds were forwarded to, the contract keeps them when not set
+    #[serde(default)]
+    pub fee_receiver: Option<Addr>,
+}
+
+/// State of the `CollectFees` call whose swaps are still settling
+#[cw_serde]
+pub struct CollectionRun {
+    pub executor: Addr,
+    /// distribute `received` to the distribution targets once every swap settled
+    pub auto_distribute: bool,
+    /// swaps waiting for their reply
+    pub pending: u64,
+    pub received: Uint128,
+    pub mode: CollectMode,
+    /// amounts queued by the run per approver and offer asset, claims collected in reply
+    /// keep them reserved and add their own
+    #[serde(default)]
+    pub taken: Vec<(Addr, String, Uint128)>,
+}
+
+/// Requirement whose last best-effort collection failed, retried by the executors
+#[cw_serde]
+pub struct FailedCollection {
+    /// collection id of the first failure, keys the entry with the approver
+    pub id: u64,
+    pub requirement: CollectFeeRequirement,
+    pub offer_asset: AssetInfo,
+    pub error: String,
+    /// consecutive failed collections of the requirement, skipped retries included
+    pub attempts: u32,
+    pub last_try_height: u64,
+    pub last_try_time: Timestamp,
+}
+
+/// Rate limits of an executor over a window of `window` seconds
+#[cw_serde]
+pub struct ExecutorQuota {
+    pub window: u64,
+    /// collection calls per window
+    pub max_calls: Option<u32>,
+    /// fees offered by the collections of a window, quoted in the distribute token when queued
+    pub max_value: Option<Uint128>,
+}
+
+/// Quota consumed by an executor in its current window
+#[cw_serde]
+pub struct ExecutorUsage {
+    pub window_start: Timestamp,
+    pub calls: u32,
+    pub value: Uint128,
+}
+
+/// Collection activity of an executor
+#[cw_serde]
+pub struct ExecutorStats {
+    pub executor: Addr,
+    /// collection calls made by the executor
+    pub calls: u64,
+    pub requirements: u64,
+    /// requirements skipped or rejected instead of collected
+    pub skipped: u64,
+    pub last_call_height: u64,
+    pub last_call_time: Timestamp,
+    /// distribute token received by the collections of the executor
+    pub total_proceeds: Uint128,
+}
+
 pub const CONFIG: Item<Config> = Item::new(CONFIG_KEY);
 pub const DISTRIBUTION_TARGETS: Item<Vec<DistributeTarget>> = Item::new(DISTRIBUTION_TARGET);
 pub const EXECUTORS: Map<&Addr, bool> = Map::new("executors");
+pub const REGISTERED_APPROVERS: Map<&Addr, RegisteredApprover> = Map::new("registered_approvers");
+pub const APPROVER_SETTINGS: Map<&Addr, ApproverSettings> = Map::new("approver_settings");
+pub const LAST_COLLECTED: Map<&Addr, Timestamp> = Map::new("last_collected");
+pub const NATIVE_RESERVES: Map<&str, NativeReserve> = Map::new("native_reserves");
+pub const ROUTERS: Map<&str, RouterInfo> = Map::new("routers");
+/// explicit resolution of pool tokens, see `helpers::asset_info_from_string`
+pub const ASSET_REGISTRY: Map<&str, AssetInfo> = Map::new("asset_registry");
+/// failed collections by approver and id, removed once collected again
+pub const FAILED_COLLECTIONS: Map<(&Addr, u64), FailedCollection> = Map::new("failed_collections");
+/// key of the failed collection retried last, the next retry continues after it
+pub const RETRY_CURSOR: Item<(Addr, u64)> = Item::new("retry_cursor");
+/// sub-treasuries and buckets allowed to receive the proceeds of collections, by name
+pub const FEE_RECEIVERS: Map<&str, Addr> = Map::new("fee_receivers");
+/// retentions by approver and asset, the approver and the owner overwrite each other's entries
+pub const RETENTIONS: Map<(&Addr, &str), AssetRetention> = Map::new("retentions");
+pub const EXECUTOR_QUOTAS: Map<&Addr, ExecutorQuota> = Map::new("executor_quotas");
+pub const EXECUTOR_USAGE: Map<&Addr, ExecutorUsage> = Map::new("executor_usage");
+/// activity of the executors, permissionless keepers are not tracked
+pub const EXECUTOR_STATS: Map<&Addr, ExecutorStats> = Map::new("executor_stats");
+/// proceeds of the labelled collections by (epoch, label)
+pub const REVENUE_BY_SOURCE: Map<(u64, &str), Uint128> = Map::new("revenue_by_source");
+/// labels the collections may attribute their proceeds to, maintained by the owner
+pub const REVENUE_LABELS: Map<&str, bool> = Map::new("revenue_labels");
+/// when not empty, only these offer assets are collected
+pub const ALLOWED_OFFER_ASSETS: Map<&str, AssetInfo> = Map::new("allowed_offer_assets");
+/// offer assets that are never collected
+pub const DENIED_OFFER_ASSETS: Map<&str, AssetInfo> = Map::new("denied_offer_assets");
+pub const COLLECT_THRESHOLDS: Map<&str, CollectThreshold> = Map::new("collect_thresholds");
+pub const CHUNK_LIMITS: Map<&str, ChunkLimit> = Map::new("chunk_limits");
+pub const CHUNK_SPENT: Map<&str, ChunkSpent> = Map::new("chunk_spent");
+/// collectable amount left by the chunk limit, per offer asset and approver
+pub const QUEUED_REMAINDERS: Map<(&str, &Addr), Uint128> = Map::new("queued_remainders");
+pub const PENDING_COLLECTIONS: Map<u64, PendingCollection> = Map::new("pending_collections");
+/// requirements collected once the claim replying under the same id settled
+pub const PENDING_CLAIMS: Map<u64, CollectFeeRequirement> = Map::new("pending_claims");
+/// distribute token balance of the contract after the last measured swap
+pub const COLLECTION_SNAPSHOT: Item<Uint128> = Item::new("collection_snapshot");
+pub const COLLECTION_COUNT: Item<u64> = Item::new("collection_count");
+pub const COLLECTION_HISTORY: Map<u64, CollectionRecord> = Map::new("collection_history");
+pub const COLLECTION_RUN: Item<CollectionRun> = Item::new("collection_run");